@@ -0,0 +1,195 @@
+use crate::buffer::Buffer;
+use crate::error_boundary::ErrorBoundary;
+use crate::event_bus::EventBus;
+use crate::widget::{EventResult, WidgetTrait};
+use crossterm::event;
+
+// Requests a widget makes to the app's view stack.
+pub enum Navigation {
+    // Full-screen views hide everything below them, modal views are drawn on top.
+    Push { view: Box<dyn WidgetTrait>, modal: bool },
+    Pop,
+}
+
+pub struct App {
+    pub width: u16,
+    pub height: u16,
+    pub running: bool,
+    // The root view is always at index 0 and can't be popped.
+    views: Vec<Box<dyn WidgetTrait>>,
+    modal: Vec<bool>,
+    event_bus: EventBus,
+}
+
+impl App {
+    pub fn new(width: u16, height: u16, root: Box<dyn WidgetTrait>) -> App {
+        return App {
+            width,
+            height,
+            running: true,
+            views: vec![root],
+            modal: vec![false],
+            event_bus: EventBus::new(),
+        };
+    }
+
+    pub fn init(&mut self) {
+        self.views[0].init();
+    }
+
+    pub fn depth(&self) -> usize {
+        return self.views.len();
+    }
+
+    // Views are wrapped in an error boundary like the root's widgets, so one
+    // that panics shows its error instead of taking the app down.
+    pub fn push(&mut self, view: Box<dyn WidgetTrait>, modal: bool) {
+        let mut view: Box<dyn WidgetTrait> = Box::new(ErrorBoundary::new(view));
+        view.init();
+        self.views.push(view);
+        self.modal.push(modal);
+    }
+
+    pub fn pop(&mut self) {
+        if self.views.len() > 1 {
+            self.views.pop();
+            self.modal.pop();
+        }
+    }
+
+    // Events only go to the top view. Esc pops it when nothing handled the
+    // event, and quits once we're back at the root.
+    pub fn handle_event(&mut self, event_to_handle: &event::Event) {
+        let result = self.views.last_mut().unwrap().handle_event(event_to_handle);
+        if result == EventResult::Unhandled {
+            match event_to_handle {
+                event::Event::Key(event) => {
+                    match event.code {
+                        event::KeyCode::Esc => {
+                            if self.views.len() > 1 {
+                                self.pop();
+                            } else {
+                                self.running = false;
+                            }
+                        },
+                        _ => {}
+                    }
+                },
+                _ => {}
+            };
+        };
+        self.apply_navigation();
+    }
+
+    pub fn update(&mut self) {
+        self.views.last_mut().unwrap().update();
+        self.apply_navigation();
+
+        self.event_bus.collect(&mut self.views);
+        self.event_bus.deliver(&mut self.views);
+    }
+
+    // Where to show the terminal cursor, if the top view wants one.
+    pub fn cursor_position(&self) -> Option<(u16, u16)> {
+        let view = self.views.last().unwrap();
+        let (x, y) = view.get_cursor_position()?;
+        let (width, height) = view.get_size();
+        let view_x = self.width.saturating_sub(width) / 2;
+        let view_y = self.height.saturating_sub(height) / 2;
+        return Some((view_x + x, view_y + y));
+    }
+
+    pub fn draw(&mut self) -> Buffer {
+        let mut buffer = Buffer::new(self.width, self.height);
+        let first_visible = self.modal.iter().rposition(|modal| !modal).unwrap_or(0);
+        for i in first_visible..self.views.len() {
+            self.views[i].draw();
+            let view_buffer = self.views[i].generate_buffer();
+            let x = self.width.saturating_sub(view_buffer.width) / 2;
+            let y = self.height.saturating_sub(view_buffer.height) / 2;
+            buffer.blit(x, y, &view_buffer);
+        }
+        return buffer;
+    }
+
+    fn apply_navigation(&mut self) {
+        let mut requests = vec![];
+        for view in self.views.iter_mut() {
+            requests.append(&mut view.take_navigation_requests());
+        }
+        for request in requests {
+            match request {
+                Navigation::Push { view, modal } => { self.push(view, modal) },
+                Navigation::Pop => { self.pop() },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod app_tests {
+    use super::*;
+    use crate::test_support::panicky;
+    use crate::text_view_widget;
+
+    fn key(code: event::KeyCode) -> event::Event {
+        return event::Event::Key(event::KeyEvent::new(code, event::KeyModifiers::NONE));
+    }
+
+    fn text_view(title: &str) -> Box<dyn WidgetTrait> {
+        return Box::new(text_view_widget::new(10, 4, title, vec![]));
+    }
+
+    #[test]
+    fn esc_pops_views_before_quitting() {
+        let mut app = App::new(20, 10, text_view("Root"));
+        app.init();
+        app.push(text_view("Detail"), false);
+        app.push(text_view("Help"), true);
+
+        app.handle_event(&key(event::KeyCode::Esc));
+        assert_eq!(app.depth(), 2);
+        app.handle_event(&key(event::KeyCode::Esc));
+        assert_eq!(app.depth(), 1);
+        assert!(app.running);
+        app.handle_event(&key(event::KeyCode::Esc));
+        assert!(!app.running);
+    }
+
+    #[test]
+    fn a_pushed_view_that_panics_is_caught() {
+        let mut app = App::new(60, 6, text_view("Root"));
+        app.init();
+        app.push(Box::new(panicky()), false);
+
+        app.handle_event(&key(event::KeyCode::Enter));
+        let buffer = app.draw();
+        let title: String = (2..19).map(|i| buffer[i].c).collect();
+        assert_eq!(title, "Panicky (crashed)");
+        app.handle_event(&key(event::KeyCode::Esc));
+        assert_eq!(app.depth(), 1);
+    }
+
+    #[test]
+    fn modal_views_draw_over_the_view_below() {
+        let mut app = App::new(20, 10, Box::new(text_view_widget::new(20, 10, "Root", vec![])));
+        app.init();
+        app.push(text_view("Help"), true);
+        let buffer = app.draw();
+
+        // Root's title at the top left, the modal's border in the middle.
+        let root_title: String = (2..6).map(|i| buffer[i].c).collect();
+        assert_eq!(root_title, "Root");
+        assert_eq!(buffer[4 * 20 + 5].c, '|');
+    }
+
+    #[test]
+    fn full_screen_views_hide_the_view_below() {
+        let mut app = App::new(20, 10, Box::new(text_view_widget::new(20, 10, "Root", vec![])));
+        app.init();
+        app.push(text_view("Detail"), false);
+        let buffer = app.draw();
+
+        assert_eq!(buffer[2].c, ' ');
+    }
+}
@@ -5,12 +5,19 @@ use crossterm::style;
 use crossterm::event;
 use crossterm::cursor;
 
+use termod::app::App;
 use termod::buffer::Buffer;
-use termod::character::Character;
-use termod::widget::{self, WidgetTrait};
-use termod::{colours, dashboard_widget, todo_widget};
+use termod::container_widget::{self, Layout};
+use termod::error_boundary::{self, ErrorBoundary};
+use termod::{cli, dashboard_widget, todo_store, todo_widget};
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
+    error_boundary::install_panic_hook();
     let mut stdout: Stdout = stdout();
     terminal::enable_raw_mode().unwrap();
     stdout.execute(terminal::EnterAlternateScreen).unwrap();
@@ -18,72 +25,30 @@ fn main() {
 
     let (width, height) = terminal::size().unwrap();
 
-    let mut main_buffer= Buffer::new(width, height);
     let mut prev_buffer = Buffer::new(width, height);
-    widget::add_buffer_border(&mut main_buffer, colours::LIGHT_GREY);
-    
-    let mut dashboard_widget = dashboard_widget::new(width-2, height-2, 0, 0);
-    dashboard_widget.init();
-
-    let mut todo_widget = todo_widget::new(width-2, height-2, 0, 0);
-    todo_widget.init();
-
-    let mut widgets: Vec<Box<dyn WidgetTrait>> = vec![
-        Box::new(todo_widget),
-        Box::new(dashboard_widget),
-    ];
-    let mut active_widget_index = 0;
-    let mut active_widget_changed= true;
-
-    loop {
+
+    let rects = container_widget::child_rects(width, height, Layout::Tabs, 2);
+    let todo_widget = todo_widget::new(rects[0].width, rects[0].height, 0, 0, todo_store::open_default_store());
+    let dashboard_widget = dashboard_widget::new(rects[1].width, rects[1].height, 0, 0);
+    let root = container_widget::new(width, height, "termod", Layout::Tabs, vec![
+        Box::new(ErrorBoundary::new(Box::new(todo_widget))),
+        Box::new(ErrorBoundary::new(Box::new(dashboard_widget))),
+    ]);
+    let mut app = App::new(width, height, Box::new(root));
+    app.init();
+
+    while app.running {
         // event
         if event::poll(std::time::Duration::from_millis(33)).unwrap() {
             let event = event::read().unwrap();
-            widgets[active_widget_index].handle_event(&event);
-            match event {
-                event::Event::Key(event) => {
-                    match event.code {
-                        event::KeyCode::Esc => { break },
-                        event::KeyCode::Tab => { 
-                            active_widget_index += 1; 
-                            active_widget_index = active_widget_index % widgets.len();
-                            active_widget_changed = true;
-                        }
-                        _ => {}
-                    }
-                },
-                _ => {}
-            };
+            app.handle_event(&event);
         };
 
         // update
-        widgets[active_widget_index].update();
+        app.update();
 
         // draw
-        widgets[active_widget_index].draw();
-        let active_widget_buffer = widgets[active_widget_index].generate_buffer();
-        let insert_pos = width + 1;
-        main_buffer = main_buffer.merge(insert_pos as usize, &active_widget_buffer).unwrap();
-
-        if active_widget_changed {
-            let mut title_str_pos = 2;
-            for i in 0..widgets.len() {
-                let title_str = widgets[i].get_title();
-                let mut title_chars = Character::vec_from_string(title_str, None, None);
-                if i == active_widget_index {
-                    for c in 0..title_chars.len() {
-                        title_chars[c].highlight();
-                    }
-                }
-
-                for j in 0..title_chars.len() {
-                    main_buffer[title_str_pos + j] = title_chars[j].clone();
-                }
-
-                title_str_pos += title_chars.len() + 1;
-            }
-            active_widget_changed = false;
-        }
+        let main_buffer = app.draw();
 
         stdout
             .queue(terminal::Clear(terminal::ClearType::Purge)).unwrap()
@@ -108,6 +73,15 @@ fn main() {
                 .queue(style::SetAttribute(style::Attribute::Reset)).unwrap();
         };
 
+        match app.cursor_position() {
+            Some((x, y)) => {
+                stdout
+                    .queue(cursor::MoveTo(x, y)).unwrap()
+                    .queue(cursor::Show).unwrap();
+            },
+            None => { stdout.queue(cursor::Hide).unwrap(); },
+        };
+
         stdout.flush().unwrap();
         prev_buffer = main_buffer.clone();
     };
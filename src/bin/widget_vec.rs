@@ -1,10 +1,11 @@
 use termod::{dashboard_widget, todo_widget};
+use termod::todo_store::MemoryStore;
 use termod::widget::WidgetTrait;
 
 fn main() {
     let mut widgets: Vec<Box<dyn WidgetTrait>> = vec![];
     let dashboard_widget = dashboard_widget::new(10, 10, 0, 0);
-    let second_widget= todo_widget::new(10, 10, 0, 0);
+    let second_widget= todo_widget::new(10, 10, 0, 0, Box::new(MemoryStore::default()));
    
     widgets.push(Box::new(dashboard_widget));
     widgets.push(Box::new(second_widget));
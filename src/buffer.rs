@@ -29,6 +29,10 @@ impl Buffer {
         return self.characters.len();
     }
 
+    pub fn is_empty(&self) -> bool {
+        return self.characters.is_empty();
+    }
+
     pub fn merge(&self, position_to_insert: usize, other: &Buffer) -> Result<Buffer, BufferMergeError> {
         let x_offset = position_to_insert % self.width as usize;
         let y_offset = position_to_insert / self.width as usize;
@@ -55,7 +59,26 @@ impl Buffer {
         return Ok(new_buff);
     }
 
-    pub fn insert_char_slice(&mut self, position: usize, chars: &Vec<Character>) {
+    // Copies `other` into this buffer with its top left corner at (x, y),
+    // dropping anything that falls outside.
+    pub fn blit(&mut self, x: u16, y: u16, other: &Buffer) {
+        for other_y in 0..other.height {
+            let this_y = other_y as usize + y as usize;
+            if this_y >= self.height as usize {
+                break;
+            };
+            for other_x in 0..other.width {
+                let this_x = other_x as usize + x as usize;
+                if this_x >= self.width as usize {
+                    break;
+                };
+                let other_pos = other_y as usize * other.width as usize + other_x as usize;
+                self.characters[this_y * self.width as usize + this_x] = other[other_pos].clone();
+            }
+        }
+    }
+
+    pub fn insert_char_slice(&mut self, position: usize, chars: &[Character]) {
         for i in 0..chars.len() {
             let offset = i + position;
             if self.characters[offset] != chars[i] {
@@ -91,7 +114,7 @@ mod insert_char_slice_tests {
     #[test]
     fn inserts_the_chars_at_the_expected_places() {
         let mut buffer_one = Buffer::new(6, 3);
-        let string_chars = Character::vec_from_string(&"----".to_string(), None, None);
+        let string_chars = Character::vec_from_string("----", None, None);
 
         buffer_one.insert_char_slice(7, &string_chars);
         let expected_res = [
@@ -113,7 +136,6 @@ mod merge_tests {
     use crate::{character, colours};
 
     use super::*;
-    use crossterm::style;
 
     fn type_of<T>(_: &T) -> &'static str {
         type_name::<T>()
@@ -190,3 +212,25 @@ mod merge_tests {
         }
     }
 }
+
+#[cfg(test)]
+mod blit_tests {
+    use super::*;
+
+    #[test]
+    fn blit_clips_to_the_buffer_bounds() {
+        let mut buffer_one = Buffer::new(4, 2);
+        let mut buffer_two = Buffer::new(3, 3);
+        for i in 0..buffer_two.len() {
+            buffer_two[i].c = '#';
+        };
+
+        buffer_one.blit(2, 1, &buffer_two);
+        let expected_res = [
+            ' ', ' ', ' ', ' ',
+            ' ', ' ', '#', '#',
+        ];
+        let result: Vec<char> = buffer_one.characters.into_iter().map(|x| x.c).collect();
+        assert_eq!(result, expected_res);
+    }
+}
@@ -53,7 +53,7 @@ impl Character {
         };
     }
 
-    pub fn vec_from_string(string: &String, colour: Option<style::Color>, attributes: Option<style::Attributes>) -> Vec<Character> {
+    pub fn vec_from_string(string: &str, colour: Option<style::Color>, attributes: Option<style::Attributes>) -> Vec<Character> {
         let mut result: Vec<Character> = vec![];
         let string_chars: Vec<char> = string.chars().collect();
         let colour_for_chars = match colour {
@@ -0,0 +1,103 @@
+use std::io::Write;
+
+use crate::report::{self, DateRange};
+use crate::todo::TodoItems;
+use crate::{export, icalendar, taskwarrior, todo_store, todo_txt};
+
+fn usage() -> String {
+    return [
+        "usage: termod [command]",
+        "",
+        "With no command, opens the todo list.",
+        "",
+        "  report [--from DATE] [--to DATE]",
+        "      print a Markdown standup report, yesterday and today by default.",
+        "      DATE is YYYY-MM-DD, today or yesterday",
+        "  import FORMAT FILE",
+        "      add the todos in FILE to the list, updating ones imported before",
+        "  export FORMAT [FILE]",
+        "      write the list to FILE, or print it",
+        "",
+        "FORMAT is one of: todotxt, ics, taskwarrior, and for export only",
+        "markdown and csv",
+    ].join("\n");
+}
+
+// Runs a command given on the command line. Returns None when there isn't
+// one and the TUI should start, otherwise the exit code.
+pub fn run(args: &[String]) -> Option<i32> {
+    let (command, rest) = args.split_first()?;
+    let result = match command.as_str() {
+        "report" => report(rest),
+        "import" => import(rest),
+        "export" => export(rest),
+        "help" | "--help" | "-h" => Ok(usage()),
+        _ => Err(format!("unknown command {}\n\n{}", command, usage())),
+    };
+    match result {
+        Ok(output) => {
+            let _ = writeln!(std::io::stdout(), "{}", output);
+            return Some(0);
+        },
+        Err(error) => {
+            let _ = writeln!(std::io::stderr(), "termod: {}", error);
+            return Some(1);
+        },
+    }
+}
+
+fn report(args: &[String]) -> Result<String, String> {
+    let range = DateRange::from_args(args, chrono::Local::now().date_naive())?;
+    let mut store = todo_store::open_default_store();
+    let items = store.load().map_err(|error| format!("couldn't read {}: {}", store.describe(), error))?;
+    return Ok(report::standup_report(&items, range, chrono::Utc::now()));
+}
+
+fn parse_items(format: &str, text: &str) -> Result<TodoItems, String> {
+    return match format {
+        "todotxt" => Ok(TodoItems::new(todo_txt::parse(text, chrono::Utc::now()))),
+        "ics" => Ok(TodoItems::new(icalendar::parse(text, chrono::Utc::now())?)),
+        "taskwarrior" => taskwarrior::parse(text, chrono::Utc::now()),
+        "markdown" | "csv" => Err(format!("{} can only be exported", format)),
+        _ => Err(format!("unknown format {}", format)),
+    };
+}
+
+fn import(args: &[String]) -> Result<String, String> {
+    let (format, path) = match args {
+        [format, path] => (format, path),
+        _ => return Err(format!("import needs a format and a file\n\n{}", usage())),
+    };
+    let text = std::fs::read_to_string(path).map_err(|error| format!("couldn't read {}: {}", path, error))?;
+    let imported = parse_items(format, &text)?;
+
+    let mut store = todo_store::open_default_store();
+    let mut items = store.load().map_err(|error| format!("couldn't read {}: {}", store.describe(), error))?;
+    items.assign_missing_ids();
+    let count = imported.todo_items.len() + imported.done_items.len() + imported.removed_items.len();
+    items.merge(imported);
+    store.save(&items).map_err(|error| format!("couldn't write {}: {}", store.describe(), error))?;
+    return Ok(format!("imported {} todos into {}", count, store.describe()));
+}
+
+fn export(args: &[String]) -> Result<String, String> {
+    let (format, path) = match args {
+        [format] => (format, None),
+        [format, path] => (format, Some(path)),
+        _ => return Err(format!("export needs a format\n\n{}", usage())),
+    };
+    let mut store = todo_store::open_default_store();
+    let mut items = store.load().map_err(|error| format!("couldn't read {}: {}", store.describe(), error))?;
+    // Exports refer to items by UUID, so any new ones are kept for next time.
+    if items.assign_missing_ids() {
+        store.save(&items).map_err(|error| format!("couldn't write {}: {}", store.describe(), error))?;
+    }
+    let text = export::write(format, &items, chrono::Utc::now())?;
+    return match path {
+        Some(path) => {
+            std::fs::write(path, text).map_err(|error| format!("couldn't write {}: {}", path, error))?;
+            Ok(format!("exported {} todos to {}", items.todo_items.len() + items.done_items.len(), path))
+        },
+        None => Ok(text.trim_end().to_string()),
+    };
+}
@@ -3,4 +3,6 @@ use crossterm::style;
 pub const GREY: style::Color = style::Color::Rgb { r: 100, g: 100, b: 100 };
 pub const LIGHT_GREY: style::Color = style::Color::Rgb { r: 200, g: 200, b: 200 };
 pub const ORANGE: style::Color = style::Color::Rgb { r: 245, g: 158, b: 66 };
+pub const YELLOW: style::Color = style::Color::Rgb { r: 235, g: 210, b: 80 };
+pub const RED: style::Color = style::Color::Rgb { r: 230, g: 80, b: 80 };
 pub const LIGHT_BLUE: style::Color = style::Color::Rgb { r: 66, g: 152, b: 245 };
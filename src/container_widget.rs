@@ -0,0 +1,271 @@
+use crate::app::Navigation;
+use crate::buffer::Buffer;
+use crate::character::Character;
+use crate::colours;
+use crate::event_bus::AppEvent;
+use crate::widget::{self, EventResult, Widget, WidgetTrait};
+use crossterm::event;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layout {
+    // One child visible at a time inside a border, with the child titles as tabs.
+    Tabs,
+    // Children side by side, splitting the width evenly.
+    Horizontal,
+    // Children stacked, splitting the height evenly.
+    Vertical,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+pub struct ContainerState {
+    pub children: Vec<Box<dyn WidgetTrait>>,
+    pub active_child: usize,
+    pub layout: Layout,
+    buffer: Buffer,
+}
+
+impl ContainerState {
+    fn visible_children(&self) -> Vec<usize> {
+        if self.layout == Layout::Tabs {
+            if self.children.len() == 0 {
+                return vec![];
+            };
+            return vec![self.active_child];
+        } else {
+            return (0..self.children.len()).collect();
+        }
+    }
+
+    pub fn focus_next(&mut self) {
+        if self.children.len() > 0 {
+            self.active_child = (self.active_child + 1) % self.children.len();
+        }
+    }
+
+    pub fn focus_prev(&mut self) {
+        if self.children.len() > 0 {
+            self.active_child = (self.active_child + self.children.len() - 1) % self.children.len();
+        }
+    }
+}
+
+// The area each child is given inside a container of the given size. Children
+// should be created with these dimensions.
+pub fn child_rects(width: u16, height: u16, layout: Layout, child_count: usize) -> Vec<Rect> {
+    let count = child_count as u16;
+    if count == 0 {
+        return vec![];
+    };
+
+    let mut rects = vec![];
+    match layout {
+        Layout::Tabs => {
+            for _ in 0..count {
+                rects.push(Rect { x: 1, y: 1, width: width.saturating_sub(2), height: height.saturating_sub(2) });
+            }
+        },
+        Layout::Horizontal => {
+            let child_width = width / count;
+            for i in 0..count {
+                let extra = if i == count - 1 { width % count } else { 0 };
+                rects.push(Rect { x: i * child_width, y: 0, width: child_width + extra, height });
+            }
+        },
+        Layout::Vertical => {
+            let child_height = height / count;
+            for i in 0..count {
+                let extra = if i == count - 1 { height % count } else { 0 };
+                rects.push(Rect { x: 0, y: i * child_height, width, height: child_height + extra });
+            }
+        },
+    }
+    return rects;
+}
+
+impl WidgetTrait for Widget<ContainerState> {
+    fn init(&mut self) {self.init()}
+    fn handle_event(&mut self, event_to_handle: &event::Event) -> EventResult {self.handle_event(event_to_handle)}
+    fn handle_app_event(&mut self, app_event: &AppEvent) {
+        // Every child decides for itself whether it is subscribed.
+        for child in self.state.children.iter_mut() {
+            child.handle_app_event(app_event);
+        }
+        self.handle_app_event(app_event);
+    }
+    fn take_published_events(&mut self) -> Vec<AppEvent> {
+        let mut events = self.take_published_events();
+        for child in self.state.children.iter_mut() {
+            events.append(&mut child.take_published_events());
+        }
+        return events;
+    }
+    fn take_navigation_requests(&mut self) -> Vec<Navigation> {
+        let mut requests = self.take_navigation_requests();
+        for child in self.state.children.iter_mut() {
+            requests.append(&mut child.take_navigation_requests());
+        }
+        return requests;
+    }
+    fn update(&mut self) {self.update()}
+    fn draw(&mut self) {self.draw()}
+    fn generate_buffer(&mut self) -> Buffer {self.generate_buffer()}
+    fn get_title(&self) -> &String {&self.title}
+    fn get_size(&self) -> (u16, u16) {(self.width, self.height)}
+    fn get_cursor_position(&self) -> Option<(u16, u16)> {
+        let rects = child_rects(self.width, self.height, self.state.layout, self.state.children.len());
+        let child = self.state.children.get(self.state.active_child)?;
+        let (x, y) = child.get_cursor_position()?;
+        let rect = rects[self.state.active_child];
+        return Some((rect.x + x, rect.y + y));
+    }
+}
+
+fn init(myself: &mut Widget<ContainerState>) {
+    for child in myself.state.children.iter_mut() {
+        child.init();
+    }
+}
+
+fn event(myself: &mut Widget<ContainerState>, event_to_handle: &event::Event) -> EventResult {
+    let active_child = myself.state.active_child;
+    if let Some(child) = myself.state.children.get_mut(active_child) {
+        if child.handle_event(event_to_handle) == EventResult::Consumed {
+            return EventResult::Consumed;
+        };
+    };
+
+    match event_to_handle {
+        event::Event::Key(event) => {
+            match event.code {
+                event::KeyCode::Tab => { myself.state.focus_next() },
+                event::KeyCode::BackTab => { myself.state.focus_prev() },
+                _ => { return EventResult::Unhandled }
+            }
+            return EventResult::Consumed;
+        },
+        _ => { return EventResult::Unhandled }
+    }
+}
+
+fn update(myself: &mut Widget<ContainerState>) {
+    for i in myself.state.visible_children() {
+        myself.state.children[i].update();
+    }
+}
+
+fn draw(myself: &mut Widget<ContainerState>) {
+    myself.state.buffer.clear();
+    let rects = child_rects(myself.width, myself.height, myself.state.layout, myself.state.children.len());
+    for i in myself.state.visible_children() {
+        myself.state.children[i].draw();
+        let child_buffer = myself.state.children[i].generate_buffer();
+        myself.state.buffer.blit(rects[i].x, rects[i].y, &child_buffer);
+    }
+
+    if myself.state.layout == Layout::Tabs {
+        widget::add_buffer_border(&mut myself.state.buffer, colours::LIGHT_GREY);
+        let mut title_str_pos = 2;
+        for i in 0..myself.state.children.len() {
+            let mut title_chars = Character::vec_from_string(myself.state.children[i].get_title(), None, None);
+            if i == myself.state.active_child {
+                for c in 0..title_chars.len() {
+                    title_chars[c].highlight();
+                }
+            }
+
+            if title_str_pos + title_chars.len() < myself.width as usize {
+                myself.state.buffer.insert_char_slice(title_str_pos, &title_chars);
+            }
+            title_str_pos += title_chars.len() + 1;
+        }
+    }
+}
+
+fn generate_buffer(myself: &mut Widget<ContainerState>) -> Buffer {
+    return myself.state.buffer.clone();
+}
+
+pub fn new(width: u16, height: u16, title: &str, layout: Layout, children: Vec<Box<dyn WidgetTrait>>) -> Widget<ContainerState> {
+    return Widget {
+        width,
+        height,
+        title: title.to_string(),
+        init_fn: init,
+        event_fn: event,
+        app_event_fn: widget::ignore_app_event,
+        update_fn: update,
+        draw_fn: draw,
+        generate_buffer_fn: generate_buffer,
+        subscriptions: vec![],
+        published_events: vec![],
+        navigation_requests: vec![],
+        cursor_position: None,
+        state: ContainerState {
+            children,
+            active_child: 0,
+            layout,
+            buffer: Buffer::new(width, height),
+        }
+    };
+}
+
+#[cfg(test)]
+mod container_tests {
+    use super::*;
+    use crate::todo_store::MemoryStore;
+    use crate::{dashboard_widget, todo_widget};
+
+    fn key(code: event::KeyCode) -> event::Event {
+        return event::Event::Key(event::KeyEvent::new(code, event::KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn child_rects_split_evenly_with_remainder_to_the_last_child() {
+        let rects = child_rects(10, 4, Layout::Horizontal, 3);
+        assert_eq!(rects, vec![
+            Rect { x: 0, y: 0, width: 3, height: 4 },
+            Rect { x: 3, y: 0, width: 3, height: 4 },
+            Rect { x: 6, y: 0, width: 4, height: 4 },
+        ]);
+    }
+
+    #[test]
+    fn unhandled_child_events_bubble_to_the_container() {
+        let rects = child_rects(40, 20, Layout::Tabs, 2);
+        let mut container = new(40, 20, "Root", Layout::Tabs, vec![
+            Box::new(todo_widget::new(rects[0].width, rects[0].height, 0, 0, Box::new(MemoryStore::default()))),
+            Box::new(dashboard_widget::new(rects[1].width, rects[1].height, 0, 0)),
+        ]);
+        container.init();
+
+        assert_eq!(container.handle_event(&key(event::KeyCode::Down)), EventResult::Consumed);
+        assert_eq!(container.state.active_child, 0);
+        assert_eq!(container.handle_event(&key(event::KeyCode::Tab)), EventResult::Consumed);
+        assert_eq!(container.state.active_child, 1);
+        assert_eq!(container.handle_event(&key(event::KeyCode::Esc)), EventResult::Unhandled);
+    }
+
+    #[test]
+    fn tabs_highlight_the_active_child_title() {
+        let rects = child_rects(40, 20, Layout::Tabs, 2);
+        let mut container = new(40, 20, "Root", Layout::Tabs, vec![
+            Box::new(todo_widget::new(rects[0].width, rects[0].height, 0, 0, Box::new(MemoryStore::default()))),
+            Box::new(dashboard_widget::new(rects[1].width, rects[1].height, 0, 0)),
+        ]);
+        container.init();
+        container.draw();
+        let buffer = container.generate_buffer();
+
+        let title: String = (2..6).map(|i| buffer[i].c).collect();
+        assert_eq!(title, "Todo");
+        assert!(buffer[2].attributes.has(crossterm::style::Attribute::Reverse));
+        assert!(!buffer[7].attributes.has(crossterm::style::Attribute::Reverse));
+    }
+}
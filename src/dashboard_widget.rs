@@ -1,7 +1,9 @@
+use crate::app::Navigation;
 use crate::buffer::Buffer;
 use crate::character::Character;
 use crate::colours;
-use crate::widget::{self, Widget, WidgetTrait};
+use crate::event_bus::{AppEvent, AppEventKind};
+use crate::widget::{self, EventResult, Widget, WidgetTrait};
 use crossterm::event;
 use rand::rngs::ThreadRng;
 use rand::Rng;
@@ -12,15 +14,23 @@ pub struct DashboardState {
     pub bg_buffer: Buffer,
     pub debug_x: usize,
     pub debug_y: usize,
+    // Ids and titles of the items being worked on, latest last.
+    pub working_on: Vec<(u64, String)>,
+    pub completed_count: usize,
 }
 
 impl WidgetTrait for Widget<DashboardState> {
     fn init(&mut self) {self.init()}
-    fn handle_event(&mut self, event_to_handle: &event::Event) {self.handle_event(event_to_handle)}
+    fn handle_event(&mut self, event_to_handle: &event::Event) -> EventResult {self.handle_event(event_to_handle)}
+    fn handle_app_event(&mut self, app_event: &AppEvent) {self.handle_app_event(app_event)}
+    fn take_published_events(&mut self) -> Vec<AppEvent> {self.take_published_events()}
+    fn take_navigation_requests(&mut self) -> Vec<Navigation> {self.take_navigation_requests()}
     fn update(&mut self) {self.update()}
     fn draw(&mut self) {self.draw()}
     fn generate_buffer(&mut self) -> Buffer {self.generate_buffer()}
     fn get_title(&self) -> &String {&self.title}
+    fn get_size(&self) -> (u16, u16) {(self.width, self.height)}
+    fn get_cursor_position(&self) -> Option<(u16, u16)> {self.cursor_position}
 }
 
 fn dashboard_init(myself: &mut Widget<DashboardState>) {
@@ -30,18 +40,40 @@ fn dashboard_init(myself: &mut Widget<DashboardState>) {
     myself.state.bg_buffer = Buffer::new(myself.width, myself.height);
 }
 
-fn dashboard_event(myself: &mut Widget<DashboardState>, event_to_handle: &event::Event) {
+fn dashboard_event(myself: &mut Widget<DashboardState>, event_to_handle: &event::Event) -> EventResult {
     match event_to_handle {
         event::Event::Key(event) => {
             match event.code {
-                event::KeyCode::Left => { myself.state.debug_x -= 1 },
-                event::KeyCode::Right => { myself.state.debug_x += 1 },
-                event::KeyCode::Up => { myself.state.debug_y -= 1 },
-                event::KeyCode::Down => { myself.state.debug_y += 1 },
-                _ => {}
+                event::KeyCode::Left => { myself.state.debug_x = myself.state.debug_x.saturating_sub(1) },
+                event::KeyCode::Right => {
+                    if myself.state.debug_x + (myself.state.debug_buffer.width as usize) < myself.width as usize {
+                        myself.state.debug_x += 1;
+                    }
+                },
+                event::KeyCode::Up => { myself.state.debug_y = myself.state.debug_y.saturating_sub(1) },
+                event::KeyCode::Down => {
+                    if myself.state.debug_y + (myself.state.debug_buffer.height as usize) < myself.height as usize {
+                        myself.state.debug_y += 1;
+                    }
+                },
+                _ => { return EventResult::Unhandled }
+            }
+            return EventResult::Consumed;
+        },
+        _ => { return EventResult::Unhandled }
+    }
+}
+
+fn dashboard_app_event(myself: &mut Widget<DashboardState>, app_event: &AppEvent) {
+    match app_event {
+        AppEvent::TodoCompleted { .. } => { myself.state.completed_count += 1 },
+        AppEvent::TodoReopened { .. } => { myself.state.completed_count = myself.state.completed_count.saturating_sub(1) },
+        AppEvent::WorkingChanged { id, title, working } => {
+            myself.state.working_on.retain(|(working_id, _)| working_id != id);
+            if *working {
+                myself.state.working_on.push((*id, title.clone()));
             }
         },
-        _ => {}
     }
 }
 
@@ -64,6 +96,23 @@ fn dashboard_draw(myself: &mut Widget<DashboardState>) {
     let mut debug_buffer = myself.state.debug_buffer.clone();
     let fps_chars = Character::vec_from_string(&format!("Frame Count: {}", myself.state.frame_count), None, None);
     debug_buffer.insert_char_slice(0, &fps_chars);
+
+    // Rows inside the border, padded so shorter text clears what was there before.
+    let inner_width = debug_buffer.width as usize - 2;
+    let working_on = match myself.state.working_on.last() {
+        Some((_, title)) => title.clone(),
+        None => "-".to_string(),
+    };
+    let lines = [
+        "Working on:".to_string(),
+        working_on,
+        format!("Completed: {}", myself.state.completed_count),
+    ];
+    for (row, line) in lines.iter().enumerate() {
+        let text: String = format!("{:<width$}", line, width = inner_width).chars().take(inner_width).collect();
+        let line_chars = Character::vec_from_string(&text, None, None);
+        debug_buffer.insert_char_slice((row + 1) * debug_buffer.width as usize + 1, &line_chars);
+    }
     myself.state.debug_buffer = debug_buffer;
 }
 
@@ -71,7 +120,7 @@ fn dashboard_generate_buffer(myself: &mut Widget<DashboardState>) -> Buffer {
     let bg_buffer = &myself.state.bg_buffer;
     let pos_to_insert = myself.state.debug_x + myself.state.debug_y * myself.width as usize;
     let debug_buffer = &myself.state.debug_buffer;
-    bg_buffer.merge(pos_to_insert, &debug_buffer).unwrap()
+    bg_buffer.merge(pos_to_insert, debug_buffer).unwrap()
 }
 
 pub fn new(width: u16, height: u16, x: usize, y: usize) -> Widget<DashboardState> {
@@ -81,15 +130,51 @@ pub fn new(width: u16, height: u16, x: usize, y: usize) -> Widget<DashboardState
         title: "Dashboard".to_string(),
         init_fn: dashboard_init,
         event_fn: dashboard_event,
+        app_event_fn: dashboard_app_event,
         update_fn: dashboard_update,
         draw_fn: dashboard_draw,
         generate_buffer_fn: dashboard_generate_buffer,
+        subscriptions: vec![AppEventKind::TodoCompleted, AppEventKind::TodoReopened, AppEventKind::WorkingChanged],
+        published_events: vec![],
+        navigation_requests: vec![],
+        cursor_position: None,
         state: DashboardState { 
             frame_count: 0, 
             debug_x: x, 
             debug_y: y,
             bg_buffer: Buffer::new(0, 0),
             debug_buffer: Buffer::new(0, 0),
+            working_on: vec![],
+            completed_count: 0,
         }
     };
 }
+
+#[cfg(test)]
+mod app_event_tests {
+    use super::*;
+
+    #[test]
+    fn reopening_a_todo_takes_it_off_the_completed_count() {
+        let mut dashboard = new(40, 20, 0, 0);
+        let title = "Feed dogs".to_string();
+        dashboard.handle_app_event(&AppEvent::TodoCompleted { id: 1, title: title.clone() });
+        dashboard.handle_app_event(&AppEvent::TodoCompleted { id: 1, title: title.clone() });
+        dashboard.handle_app_event(&AppEvent::TodoReopened { id: 1, title: title.clone() });
+        assert_eq!(dashboard.state.completed_count, 1);
+        dashboard.handle_app_event(&AppEvent::TodoReopened { id: 1, title: title.clone() });
+        dashboard.handle_app_event(&AppEvent::TodoReopened { id: 1, title });
+        assert_eq!(dashboard.state.completed_count, 0);
+    }
+
+    #[test]
+    fn working_items_are_tracked_by_id() {
+        let mut dashboard = new(40, 20, 0, 0);
+        dashboard.handle_app_event(&AppEvent::WorkingChanged { id: 1, title: "Feed dgos".to_string(), working: true });
+        dashboard.handle_app_event(&AppEvent::WorkingChanged { id: 2, title: "Feed dgos".to_string(), working: true });
+        dashboard.handle_app_event(&AppEvent::WorkingChanged { id: 1, title: "Feed dogs".to_string(), working: true });
+        assert_eq!(dashboard.state.working_on, vec![(2, "Feed dgos".to_string()), (1, "Feed dogs".to_string())]);
+        dashboard.handle_app_event(&AppEvent::WorkingChanged { id: 1, title: "Feed dogs".to_string(), working: false });
+        assert_eq!(dashboard.state.working_on, vec![(2, "Feed dgos".to_string())]);
+    }
+}
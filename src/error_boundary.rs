@@ -0,0 +1,188 @@
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::app::Navigation;
+use crate::buffer::Buffer;
+use crate::event_bus::AppEvent;
+use crate::text_view_widget;
+use crate::widget::{EventResult, WidgetTrait};
+use crossterm::{cursor, event, terminal, ExecutableCommand};
+
+thread_local! {
+    static INSIDE_BOUNDARY: Cell<bool> = const { Cell::new(false) };
+    static LAST_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+// Keeps panics caught by a boundary from being printed over the UI. Panics
+// anywhere else end the app, so the terminal is restored before they go to
+// the previously installed hook, or the message would vanish with the
+// alternate screen.
+pub fn install_panic_hook() {
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if INSIDE_BOUNDARY.with(|inside| inside.get()) {
+            let location = match info.location() {
+                Some(location) => format!(" ({}:{})", location.file(), location.line()),
+                None => "".to_string(),
+            };
+            LAST_PANIC.with(|last| *last.borrow_mut() = Some(format!("{}{}", payload_message(info.payload()), location)));
+        } else {
+            let _ = terminal::disable_raw_mode();
+            let _ = io::stdout().execute(terminal::LeaveAlternateScreen);
+            let _ = io::stdout().execute(cursor::Show);
+            previous_hook(info);
+        }
+    }));
+}
+
+fn payload_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return message.to_string();
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        return message.clone();
+    }
+    return "unknown panic".to_string();
+}
+
+// Runs every callback of the wrapped widget under `catch_unwind`. The first
+// panic drops the widget and replaces it with a panel showing the message.
+pub struct ErrorBoundary {
+    title: String,
+    width: u16,
+    height: u16,
+    inner: Option<Box<dyn WidgetTrait>>,
+    error_panel: Option<Box<dyn WidgetTrait>>,
+}
+
+impl ErrorBoundary {
+    pub fn new(inner: Box<dyn WidgetTrait>) -> ErrorBoundary {
+        let (width, height) = inner.get_size();
+        return ErrorBoundary {
+            title: inner.get_title().clone(),
+            width,
+            height,
+            inner: Some(inner),
+            error_panel: None,
+        };
+    }
+
+    pub fn has_failed(&self) -> bool {
+        return self.inner.is_none();
+    }
+
+    fn guard<R>(&mut self, default: R, callback: impl FnOnce(&mut Box<dyn WidgetTrait>) -> R) -> R {
+        let inner = match self.inner.as_mut() {
+            Some(inner) => inner,
+            None => return default,
+        };
+
+        INSIDE_BOUNDARY.with(|inside| inside.set(true));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| callback(inner)));
+        INSIDE_BOUNDARY.with(|inside| inside.set(false));
+
+        match result {
+            Ok(value) => return value,
+            Err(payload) => {
+                let message = LAST_PANIC.with(|last| last.borrow_mut().take()).unwrap_or_else(|| payload_message(payload.as_ref()));
+                self.fail(message);
+                return default;
+            }
+        }
+    }
+
+    fn fail(&mut self, message: String) {
+        self.inner = None;
+        let mut lines = vec!["This widget crashed and has been stopped.".to_string(), "".to_string()];
+        lines.push(message);
+        let panel_title = format!("{} (crashed)", self.title);
+        let mut panel = text_view_widget::new(self.width, self.height, &panel_title, lines);
+        panel.init();
+        self.error_panel = Some(Box::new(panel));
+    }
+
+    fn panel(&mut self) -> &mut Box<dyn WidgetTrait> {
+        return self.error_panel.as_mut().unwrap();
+    }
+}
+
+impl WidgetTrait for ErrorBoundary {
+    fn init(&mut self) {
+        self.guard((), |inner| inner.init());
+    }
+
+    fn handle_event(&mut self, event_to_handle: &event::Event) -> EventResult {
+        if self.has_failed() {
+            return self.panel().handle_event(event_to_handle);
+        }
+        return self.guard(EventResult::Consumed, |inner| inner.handle_event(event_to_handle));
+    }
+
+    fn handle_app_event(&mut self, app_event: &AppEvent) {
+        self.guard((), |inner| inner.handle_app_event(app_event));
+    }
+
+    fn take_published_events(&mut self) -> Vec<AppEvent> {
+        return self.guard(vec![], |inner| inner.take_published_events());
+    }
+
+    fn take_navigation_requests(&mut self) -> Vec<Navigation> {
+        if self.has_failed() {
+            return self.panel().take_navigation_requests();
+        }
+        return self.guard(vec![], |inner| inner.take_navigation_requests());
+    }
+
+    fn update(&mut self) {
+        self.guard((), |inner| inner.update());
+    }
+
+    fn draw(&mut self) {
+        if self.has_failed() {
+            self.panel().draw();
+        } else {
+            self.guard((), |inner| inner.draw());
+        }
+    }
+
+    fn generate_buffer(&mut self) -> Buffer {
+        if !self.has_failed() {
+            let buffer = self.guard(None, |inner| Some(inner.generate_buffer()));
+            if let Some(buffer) = buffer {
+                return buffer;
+            }
+            self.panel().draw();
+        }
+        return self.panel().generate_buffer();
+    }
+
+    fn get_title(&self) -> &String {&self.title}
+    fn get_size(&self) -> (u16, u16) {(self.width, self.height)}
+    fn get_cursor_position(&self) -> Option<(u16, u16)> {
+        return match &self.inner {
+            Some(inner) => inner.get_cursor_position(),
+            None => None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod error_boundary_tests {
+    use super::*;
+    use crate::test_support::panicky;
+
+    #[test]
+    fn a_panic_replaces_the_widget_with_an_error_panel() {
+        let mut boundary = ErrorBoundary::new(Box::new(panicky()));
+        boundary.init();
+        let key = event::Event::Key(event::KeyEvent::new(event::KeyCode::Enter, event::KeyModifiers::NONE));
+
+        assert_eq!(boundary.handle_event(&key), EventResult::Consumed);
+        assert!(boundary.has_failed());
+
+        boundary.draw();
+        let buffer = boundary.generate_buffer();
+        let message_row: String = (0..buffer.width as usize).map(|x| buffer[3 * buffer.width as usize + x].c).collect();
+        assert!(message_row.contains("boom"), "{}", message_row);
+    }
+}
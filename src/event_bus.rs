@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+
+use crate::widget::WidgetTrait;
+
+// Guards against widgets that keep publishing in response to each other.
+const MAX_DELIVERY_ROUNDS: usize = 16;
+
+#[derive(Clone, Debug, PartialEq)]
+// Items are identified by id, as titles can change or repeat. The title is
+// for showing.
+pub enum AppEvent {
+    TodoCompleted { id: u64, title: String },
+    TodoReopened { id: u64, title: String },
+    WorkingChanged { id: u64, title: String, working: bool },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AppEventKind {
+    TodoCompleted,
+    TodoReopened,
+    WorkingChanged,
+}
+
+impl AppEvent {
+    pub fn kind(&self) -> AppEventKind {
+        return match self {
+            AppEvent::TodoCompleted { .. } => AppEventKind::TodoCompleted,
+            AppEvent::TodoReopened { .. } => AppEventKind::TodoReopened,
+            AppEvent::WorkingChanged { .. } => AppEventKind::WorkingChanged,
+        };
+    }
+}
+
+#[derive(Default)]
+pub struct EventBus {
+    queue: VecDeque<AppEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        return EventBus { queue: VecDeque::new() };
+    }
+
+    pub fn publish(&mut self, event: AppEvent) {
+        self.queue.push_back(event);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.queue.is_empty();
+    }
+
+    // Moves every widget's published events onto the bus, in widget order.
+    pub fn collect(&mut self, widgets: &mut [Box<dyn WidgetTrait>]) {
+        for widget in widgets.iter_mut() {
+            for event in widget.take_published_events() {
+                self.publish(event);
+            }
+        }
+    }
+
+    // Delivers queued events to every widget in FIFO order. Events published
+    // while handling are delivered in a following round of the same call.
+    pub fn deliver(&mut self, widgets: &mut [Box<dyn WidgetTrait>]) {
+        for _ in 0..MAX_DELIVERY_ROUNDS {
+            if self.queue.is_empty() {
+                return;
+            };
+
+            while let Some(event) = self.queue.pop_front() {
+                for widget in widgets.iter_mut() {
+                    widget.handle_app_event(&event);
+                }
+            }
+            self.collect(widgets);
+        }
+        self.queue.clear();
+    }
+}
+
+#[cfg(test)]
+mod event_bus_tests {
+    use super::*;
+    use crate::app::Navigation;
+    use crate::buffer::Buffer;
+    use crate::widget::{EventResult, Widget};
+    use crossterm::event;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct RecorderState {
+        received: Rc<RefCell<Vec<AppEvent>>>,
+    }
+
+    fn noop(_myself: &mut Widget<RecorderState>) {}
+    fn noop_event(_myself: &mut Widget<RecorderState>, _event: &event::Event) -> EventResult {
+        return EventResult::Unhandled;
+    }
+    fn record(myself: &mut Widget<RecorderState>, app_event: &AppEvent) {
+        myself.state.received.borrow_mut().push(app_event.clone());
+    }
+    fn empty_buffer(_myself: &mut Widget<RecorderState>) -> Buffer {
+        return Buffer::new(0, 0);
+    }
+
+    impl WidgetTrait for Widget<RecorderState> {
+        fn init(&mut self) {self.init()}
+        fn handle_event(&mut self, event_to_handle: &event::Event) -> EventResult {self.handle_event(event_to_handle)}
+        fn handle_app_event(&mut self, app_event: &AppEvent) {self.handle_app_event(app_event)}
+        fn take_published_events(&mut self) -> Vec<AppEvent> {self.take_published_events()}
+        fn take_navigation_requests(&mut self) -> Vec<Navigation> {self.take_navigation_requests()}
+        fn update(&mut self) {self.update()}
+        fn draw(&mut self) {self.draw()}
+        fn generate_buffer(&mut self) -> Buffer {self.generate_buffer()}
+        fn get_title(&self) -> &String {&self.title}
+        fn get_size(&self) -> (u16, u16) {(self.width, self.height)}
+        fn get_cursor_position(&self) -> Option<(u16, u16)> {self.cursor_position}
+    }
+
+    fn recorder(subscriptions: Vec<AppEventKind>, received: &Rc<RefCell<Vec<AppEvent>>>) -> Widget<RecorderState> {
+        return Widget {
+            width: 0,
+            height: 0,
+            title: "Recorder".to_string(),
+            init_fn: noop,
+            event_fn: noop_event,
+            app_event_fn: record,
+            update_fn: noop,
+            draw_fn: noop,
+            generate_buffer_fn: empty_buffer,
+            subscriptions,
+            published_events: vec![],
+            navigation_requests: vec![],
+            cursor_position: None,
+            state: RecorderState { received: Rc::clone(received) },
+        };
+    }
+
+    #[test]
+    fn delivers_collected_events_in_publish_order() {
+        let received = Rc::new(RefCell::new(vec![]));
+        let mut publisher = recorder(vec![], &Rc::new(RefCell::new(vec![])));
+        publisher.publish(AppEvent::TodoCompleted { id: 1, title: "a".to_string() });
+        publisher.publish(AppEvent::WorkingChanged { id: 2, title: "b".to_string(), working: true });
+        let mut widgets: Vec<Box<dyn WidgetTrait>> = vec![
+            Box::new(publisher),
+            Box::new(recorder(vec![AppEventKind::TodoCompleted, AppEventKind::WorkingChanged], &received)),
+        ];
+
+        let mut bus = EventBus::new();
+        bus.collect(&mut widgets);
+        bus.deliver(&mut widgets);
+
+        assert!(bus.is_empty());
+        assert_eq!(*received.borrow(), vec![
+            AppEvent::TodoCompleted { id: 1, title: "a".to_string() },
+            AppEvent::WorkingChanged { id: 2, title: "b".to_string(), working: true },
+        ]);
+    }
+
+    #[test]
+    fn only_subscribed_kinds_reach_the_handler() {
+        let received = Rc::new(RefCell::new(vec![]));
+        let mut widget = recorder(vec![AppEventKind::WorkingChanged], &received);
+        widget.handle_app_event(&AppEvent::TodoCompleted { id: 1, title: "a".to_string() });
+        widget.handle_app_event(&AppEvent::WorkingChanged { id: 2, title: "b".to_string(), working: true });
+
+        assert_eq!(*received.borrow(), vec![
+            AppEvent::WorkingChanged { id: 2, title: "b".to_string(), working: true },
+        ]);
+    }
+}
@@ -0,0 +1,174 @@
+// Export-only formats: a GitHub-flavoured Markdown checklist for pasting into
+// PRs, and CSV for spreadsheets.
+use crate::todo::{self, Section, TodoItem, TodoItems};
+use crate::{icalendar, taskwarrior, todo_txt};
+
+// The items in any format that can be exported, by the name the command line
+// and the TUI use for it.
+pub fn write(format: &str, items: &TodoItems, now: chrono::DateTime<chrono::Utc>) -> Result<String, String> {
+    return match format {
+        "todotxt" => Ok(todo_txt::write(items)),
+        "ics" => Ok(icalendar::write(items, now)),
+        "taskwarrior" => Ok(taskwarrior::write(items)),
+        "markdown" => Ok(markdown(items, now)),
+        "csv" => Ok(csv(items, now)),
+        _ => Err(format!("unknown format {}", format)),
+    };
+}
+
+fn format_time(at: chrono::DateTime<chrono::Utc>) -> String {
+    return at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string();
+}
+
+// Backslash-escapes what Markdown would otherwise read as emphasis, links,
+// code, table cells or, at the start of a title, a heading.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::new();
+    if text.starts_with('#') {
+        escaped.push('\\');
+    }
+    for character in text.chars() {
+        if matches!(character, '\\' | '*' | '_' | '`' | '[' | ']' | '|' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    return escaped;
+}
+
+fn checklist_line(item: &TodoItem, depth: usize, now: chrono::DateTime<chrono::Utc>) -> String {
+    let mut line = format!("{}- [{}] ", "  ".repeat(depth), if item.completed_at.is_some() { "x" } else { " " });
+    if let Some(priority) = item.priority {
+        line.push_str(&format!("({}) ", priority));
+    }
+    line.push_str(&escape_markdown(&item.title));
+    for tag in &item.tags {
+        line.push_str(&format!(" #{}", escape_markdown(tag)));
+    }
+    let detail = match item.completed_at {
+        Some(completed_at) => format!("completed {}, took {}", format_time(completed_at), todo::format_worked(completed_at - item.added_at)),
+        None => format!("added {}, age {}", format_time(item.added_at), todo::format_worked(now - item.added_at)),
+    };
+    return format!("{} _({})_", line, detail);
+}
+
+// Open items, then finished ones, subtasks nested under their parents.
+pub fn markdown(items: &TodoItems, now: chrono::DateTime<chrono::Utc>) -> String {
+    let mut lines: Vec<String> = vec![];
+    for (section, heading) in [(Section::TODO, "## TODO"), (Section::DONE, "## DONE")] {
+        lines.push(heading.to_string());
+        lines.push("".to_string());
+        for (index, depth) in items.full_tree(section) {
+            lines.push(checklist_line(&items.section_items(section)[index], depth, now));
+        }
+        lines.push("".to_string());
+    }
+    return lines.join("\n");
+}
+
+// Free text starting with one of these is read as a formula by spreadsheets,
+// so it gets a leading quote to keep it text.
+fn csv_text(text: &str) -> String {
+    if text.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        return format!("'{}", text);
+    }
+    return text.to_string();
+}
+
+// Quotes a field if it has a comma, quote or line break in it (RFC 4180).
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", field.replace('"', "\"\""));
+    }
+    return field.to_string();
+}
+
+pub const CSV_HEADER: [&str; 9] = [
+    "section", "title", "priority", "tags", "added_at", "completed_at", "due_at", "time_to_complete_seconds", "time_worked_seconds",
+];
+
+// One row per open or finished item. Times are RFC 3339 in UTC, durations in
+// whole seconds and tags space separated.
+pub fn csv(items: &TodoItems, now: chrono::DateTime<chrono::Utc>) -> String {
+    let mut rows = vec![CSV_HEADER.join(",")];
+    for (section, name) in [(Section::TODO, "todo"), (Section::DONE, "done")] {
+        for item in items.section_items(section) {
+            let fields = [
+                name.to_string(),
+                csv_text(&item.title),
+                item.priority.map(String::from).unwrap_or_default(),
+                csv_text(&item.tags.join(" ")),
+                item.added_at.to_rfc3339(),
+                item.completed_at.map(|at| at.to_rfc3339()).unwrap_or_default(),
+                item.due_at.map(|at| at.to_rfc3339()).unwrap_or_default(),
+                item.completed_at.map(|at| (at - item.added_at).num_seconds().to_string()).unwrap_or_default(),
+                item.time_worked(now).num_seconds().to_string(),
+            ];
+            rows.push(fields.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(","));
+        }
+    }
+    return rows.join("\r\n") + "\r\n";
+}
+
+#[cfg(test)]
+mod export_tests {
+    use super::*;
+    use crate::test_support::now;
+
+    fn items() -> TodoItems {
+        let mut items = TodoItems::empty();
+        items.add_item("Move house".to_string());
+        items.add_child_to_selected_item("Pack, \"carefully\"".to_string());
+        for item in items.todo_items.iter_mut() {
+            item.added_at = now() - chrono::TimeDelta::days(2);
+        }
+        let mut done = TodoItem::new("Write code".to_string(), now() - chrono::TimeDelta::hours(3));
+        done.completed_at = Some(now() - chrono::TimeDelta::hours(1));
+        done.priority = Some('B');
+        done.tags = vec!["work".to_string()];
+        items.push_item(done);
+        return items;
+    }
+
+    #[test]
+    fn markdown_is_a_nested_checklist() {
+        let markdown = markdown(&items(), now());
+        let lines: Vec<&str> = markdown.lines().collect();
+        assert_eq!(lines[0], "## TODO");
+        assert!(lines[2].starts_with("- [ ] Move house _(added "), "{}", lines[2]);
+        assert!(lines[2].ends_with(", age 48h00m)_"), "{}", lines[2]);
+        assert!(lines[3].starts_with("  - [ ] Pack"), "{}", lines[3]);
+        assert_eq!(lines[5], "## DONE");
+        assert!(lines[7].starts_with("- [x] (B) Write code #work _(completed "), "{}", lines[7]);
+        assert!(lines[7].ends_with(", took 2h00m)_"), "{}", lines[7]);
+    }
+
+    #[test]
+    fn csv_quotes_fields_that_need_it() {
+        let csv = csv(&items(), now());
+        let rows: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(rows[0], CSV_HEADER.join(","));
+        assert!(rows[2].starts_with("todo,\"Pack, \"\"carefully\"\"\",,,"), "{}", rows[2]);
+        assert_eq!(rows[3], "done,Write code,B,work,2024-04-05T09:00:00+00:00,2024-04-05T11:00:00+00:00,,7200,0");
+        assert_eq!(rows.len(), 5);
+    }
+
+    #[test]
+    fn markdown_escapes_titles_and_tags() {
+        let mut item = TodoItem::new("# Fix *all* [the] a|b_c".to_string(), now());
+        item.tags = vec!["snake_case".to_string()];
+        let line = checklist_line(&item, 0, now());
+        assert!(line.starts_with("- [ ] \\# Fix \\*all\\* \\[the\\] a\\|b\\_c #snake\\_case _("), "{}", line);
+    }
+
+    #[test]
+    fn csv_keeps_formulas_as_text() {
+        let mut items = TodoItems::empty();
+        items.add_item("=HYPERLINK(\"http://example.com\")".to_string());
+        items.add_item("-1".to_string());
+        let csv = csv(&items, now());
+        let rows: Vec<&str> = csv.split("\r\n").collect();
+        assert!(rows[1].starts_with("todo,\"'=HYPERLINK(\"\"http://example.com\"\")\","), "{}", rows[1]);
+        assert!(rows[2].starts_with("todo,'-1,"), "{}", rows[2]);
+    }
+}
@@ -0,0 +1,380 @@
+// Reading and writing VTODO components of iCalendar files (RFC 5545).
+use crate::todo::{self, TodoItem, TodoItems};
+
+// Lines longer than this many bytes are folded.
+const MAX_LINE_OCTETS: usize = 75;
+
+// Joins folded lines back together: a line break followed by a space or tab
+// continues the previous line.
+pub fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continued), Some(last)) => last.push_str(continued),
+            _ if line.len() > 0 => lines.push(line.to_string()),
+            _ => {},
+        }
+    }
+    return lines;
+}
+
+// Splits a line into pieces of at most 75 bytes without breaking up a
+// character, each continuation starting with a space.
+pub fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut line_octets = 0;
+    for c in line.chars() {
+        if line_octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            line_octets = 1;
+        }
+        folded.push(c);
+        line_octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    return folded;
+}
+
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+// Unescapes a TEXT value. List values like CATEGORIES are also split on
+// unescaped commas.
+fn unescape(text: &str, is_list: bool) -> Vec<String> {
+    let mut values = vec![String::new()];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') | Some('N') => values.last_mut().unwrap().push('\n'),
+                Some(escaped) => values.last_mut().unwrap().push(escaped),
+                None => {},
+            },
+            ',' if is_list => values.push(String::new()),
+            _ => values.last_mut().unwrap().push(c),
+        }
+    }
+    return values;
+}
+
+pub fn unescape_text(text: &str) -> String {
+    return unescape(text, false).remove(0);
+}
+
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+// NAME;PARAM=value;PARAM="quoted:value":VALUE
+fn parse_property(line: &str) -> Result<Property, String> {
+    let mut in_quotes = false;
+    let split = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            in_quotes = !in_quotes;
+        }
+        *c == ':' && !in_quotes
+    });
+    let (head, value) = match split {
+        Some((index, _)) => (&line[..index], &line[index + 1..]),
+        None => return Err(format!("no ':' in line '{}'", line)),
+    };
+    let mut parts = head.split(';');
+    let name = parts.next().unwrap_or("").to_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+    return Ok(Property { name, params, value: value.to_string() });
+}
+
+// DATE-TIME in UTC ("...Z"), floating or with a TZID, or a DATE. TZIDs are
+// looked up in the IANA database and ones it doesn't have are an error, so
+// times aren't silently shifted. Floating times and dates are local time, and
+// dates are due at the end of the day.
+fn parse_date_time(property: &Property) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    let value = property.value.as_str();
+    let invalid = || format!("can't read {} '{}' as a date", property.name, value);
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = chrono::NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+        return Ok(naive.and_utc());
+    }
+    let is_date = property.params.iter().any(|(key, value)| key == "VALUE" && value.eq_ignore_ascii_case("DATE"));
+    let naive = if is_date || value.len() == 8 {
+        let date = chrono::NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| invalid())?;
+        let end_of_day = if property.name == "DUE" { chrono::NaiveTime::from_hms_opt(23, 59, 0).unwrap() } else { chrono::NaiveTime::MIN };
+        date.and_time(end_of_day)
+    } else {
+        chrono::NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(|_| invalid())?
+    };
+    // Some clients put a '/' in front to say the name is globally unique.
+    let tzid = property.params.iter().find(|(key, _)| key == "TZID").map(|(_, tzid)| tzid.trim_start_matches('/'));
+    if let Some(tzid) = tzid {
+        let zone: chrono_tz::Tz = tzid.parse().map_err(|_| format!("can't read {} '{}', unknown time zone '{}'", property.name, value, tzid))?;
+        return naive.and_local_timezone(zone).earliest().map(|at| at.to_utc()).ok_or_else(invalid);
+    }
+    return naive.and_local_timezone(chrono::Local).earliest().map(|at| at.to_utc()).ok_or_else(invalid);
+}
+
+fn format_date_time(at: chrono::DateTime<chrono::Utc>) -> String {
+    return at.format("%Y%m%dT%H%M%SZ").to_string();
+}
+
+// RFC 5545 priorities run 1 (highest) to 9 (lowest), 0 meaning none.
+fn priority_from_ical(value: &str) -> Option<char> {
+    return match value.trim().parse::<u8>().ok()? {
+        1..=2 => Some('A'),
+        3..=4 => Some('B'),
+        5 => Some('C'),
+        6..=7 => Some('D'),
+        8..=9 => Some('E'),
+        _ => None,
+    };
+}
+
+fn priority_to_ical(priority: char) -> u8 {
+    return match priority {
+        'A' => 1,
+        'B' => 3,
+        'C' => 5,
+        'D' => 7,
+        _ => 9,
+    };
+}
+
+fn item_from_properties(properties: &[Property], now: chrono::DateTime<chrono::Utc>) -> Result<TodoItem, String> {
+    let mut item = TodoItem::new("".to_string(), now);
+    let mut stamp = None;
+    let mut created = None;
+    let mut status_completed = false;
+    for property in properties {
+        match property.name.as_str() {
+            "SUMMARY" => { item.title = unescape_text(&property.value) },
+            "UID" => { item.uuid = Some(property.value.clone()) },
+            "DTSTAMP" => { stamp = Some(parse_date_time(property)?) },
+            "CREATED" => { created = Some(parse_date_time(property)?) },
+            "COMPLETED" => { item.completed_at = Some(parse_date_time(property)?) },
+            "STATUS" => { status_completed = property.value.eq_ignore_ascii_case("COMPLETED") },
+            "DUE" => { item.due_at = Some(parse_date_time(property)?) },
+            "PRIORITY" => { item.priority = priority_from_ical(&property.value) },
+            "CATEGORIES" => {
+                item.tags.extend(unescape(&property.value, true).into_iter().map(|tag| tag.trim().to_string()).filter(|tag| tag.len() > 0));
+            },
+            _ => {},
+        }
+    }
+    if let Some(added_at) = created.or(stamp) {
+        item.added_at = added_at;
+    }
+    if status_completed && item.completed_at.is_none() {
+        item.completed_at = Some(stamp.unwrap_or(now));
+    }
+    return Ok(item);
+}
+
+// Every VTODO in the file, wherever it's nested. Anything else is skipped,
+// including lines that aren't properties at all.
+pub fn parse(text: &str, now: chrono::DateTime<chrono::Utc>) -> Result<Vec<TodoItem>, String> {
+    let mut items: Vec<TodoItem> = vec![];
+    let mut todo: Option<Vec<Property>> = None;
+    // Components nested inside the VTODO, like VALARM.
+    let mut nested = 0;
+    for line in unfold(text) {
+        let property = match parse_property(&line) {
+            Ok(property) => property,
+            Err(error) if todo.is_some() && nested == 0 => return Err(error),
+            Err(_) => continue,
+        };
+        let component = property.value.to_uppercase();
+        match (property.name.as_str(), todo.as_mut()) {
+            ("BEGIN", None) if component == "VTODO" => { todo = Some(vec![]) },
+            ("BEGIN", Some(_)) => { nested += 1 },
+            ("END", Some(_)) if nested > 0 => { nested -= 1 },
+            ("END", Some(properties)) if component == "VTODO" => {
+                items.push(item_from_properties(properties, now)?);
+                todo = None;
+            },
+            (_, Some(properties)) if nested == 0 => { properties.push(property) },
+            _ => {},
+        }
+    }
+    if todo.is_some() {
+        return Err("VTODO without an END".to_string());
+    }
+    return Ok(items);
+}
+
+pub fn format_item(item: &TodoItem, now: chrono::DateTime<chrono::Utc>) -> String {
+    let uid = item.uuid.clone().unwrap_or_else(todo::new_uuid);
+    let mut lines = vec![
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", uid),
+        format!("DTSTAMP:{}", format_date_time(now)),
+        format!("CREATED:{}", format_date_time(item.added_at)),
+        format!("SUMMARY:{}", escape_text(&item.title)),
+    ];
+    match item.completed_at {
+        Some(completed_at) => {
+            lines.push("STATUS:COMPLETED".to_string());
+            lines.push(format!("COMPLETED:{}", format_date_time(completed_at)));
+        },
+        None => lines.push("STATUS:NEEDS-ACTION".to_string()),
+    }
+    if let Some(due_at) = item.due_at {
+        lines.push(format!("DUE:{}", format_date_time(due_at)));
+    }
+    if let Some(priority) = item.priority {
+        lines.push(format!("PRIORITY:{}", priority_to_ical(priority)));
+    }
+    if item.tags.len() > 0 {
+        lines.push(format!("CATEGORIES:{}", item.tags.iter().map(|tag| escape_text(tag)).collect::<Vec<_>>().join(",")));
+    }
+    lines.push("END:VTODO".to_string());
+    return lines.iter().map(|line| fold(line)).collect();
+}
+
+// Open and finished items as one calendar. Removed items aren't written.
+pub fn write(items: &TodoItems, now: chrono::DateTime<chrono::Utc>) -> String {
+    let mut text = fold("BEGIN:VCALENDAR") + &fold("VERSION:2.0") + &fold("PRODID:-//termod//termod//EN");
+    for item in items.todo_items.iter().chain(items.done_items.iter()) {
+        text.push_str(&format_item(item, now));
+    }
+    text.push_str(&fold("END:VCALENDAR"));
+    return text;
+}
+
+#[cfg(test)]
+mod icalendar_tests {
+    use super::*;
+    use crate::test_support::now;
+
+    #[test]
+    fn folds_at_75_octets_without_splitting_characters() {
+        let line = format!("SUMMARY:{}", "é".repeat(40));
+        let folded = fold(&line);
+        for physical in folded.split("\r\n").filter(|physical| physical.len() > 0) {
+            assert!(physical.len() <= MAX_LINE_OCTETS, "{}", physical);
+        }
+        assert_eq!(unfold(&folded), vec![line]);
+    }
+
+    #[test]
+    fn escaping_round_trips() {
+        let text = "a; b, c\\d\nnext";
+        assert_eq!(escape_text(text), "a\\; b\\, c\\\\d\\nnext");
+        assert_eq!(unescape_text(&escape_text(text)), text);
+    }
+
+    #[test]
+    fn reads_vtodos_with_folded_and_escaped_values() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "SUMMARY:Not a todo",
+            "END:VEVENT",
+            "BEGIN:VTODO",
+            "UID:abc@example.com",
+            "DTSTAMP:20240401T090000Z",
+            "CREATED:20240401T080000Z",
+            "SUMMARY:Buy milk\\, eggs and ",
+            " bread",
+            "DUE;VALUE=DATE:20240403",
+            "PRIORITY:2",
+            "CATEGORIES:home,shop\\,ping",
+            "STATUS:COMPLETED",
+            "COMPLETED:20240402T100000Z",
+            "BEGIN:VALARM",
+            "SUMMARY:Alarm",
+            "END:VALARM",
+            "END:VTODO",
+            "END:VCALENDAR",
+        ].join("\r\n");
+        let items = parse(&text, now()).unwrap();
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!(item.title, "Buy milk, eggs and bread");
+        assert_eq!(item.added_at, chrono::DateTime::parse_from_rfc3339("2024-04-01T08:00:00Z").unwrap());
+        assert_eq!(item.completed_at.unwrap(), chrono::DateTime::parse_from_rfc3339("2024-04-02T10:00:00Z").unwrap());
+        assert_eq!(item.due_at.unwrap().with_timezone(&chrono::Local).format("%Y-%m-%d").to_string(), "2024-04-03");
+        assert_eq!(item.priority, Some('A'));
+        assert_eq!(item.tags, vec!["home", "shop,ping"]);
+    }
+
+    #[test]
+    fn written_files_read_back_the_same() {
+        let mut item = TodoItem::new("Call mom; then dad".to_string(), now() - chrono::TimeDelta::days(2));
+        item.priority = Some('C');
+        item.tags = vec!["family".to_string()];
+        item.due_at = Some(now());
+        let items = TodoItems::new(vec![item]);
+        let item = &items.todo_items[0];
+
+        let text = write(&items, now());
+        assert!(text.starts_with("BEGIN:VCALENDAR\r\n"));
+        let read = parse(&text, now()).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].title, item.title);
+        assert_eq!(read[0].added_at, item.added_at);
+        assert_eq!(read[0].due_at, item.due_at);
+        assert_eq!(read[0].priority, item.priority);
+        assert_eq!(read[0].tags, item.tags);
+        // The UID is kept, so exporting again updates the same task.
+        assert_eq!(format_item(&read[0], now()), format_item(item, now()));
+    }
+
+    #[test]
+    fn importing_an_export_adds_no_copies() {
+        // Same title and time, so only the UID tells them apart.
+        let mut items = TodoItems::new(vec![TodoItem::new("a".to_string(), now()), TodoItem::new("a".to_string(), now())]);
+        let text = write(&items, now());
+        items.merge(TodoItems::new(parse(&text, now()).unwrap()));
+        assert_eq!(items.todo_items.len(), 2);
+        assert_eq!(write(&items, now()), text);
+    }
+
+    #[test]
+    fn an_unterminated_vtodo_is_an_error() {
+        assert!(parse("BEGIN:VTODO\r\nSUMMARY:a\r\n", now()).is_err());
+    }
+
+    #[test]
+    fn lines_outside_vtodos_that_arent_properties_are_skipped() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "X-BROKEN-CLIENT-NOTE",
+            "BEGIN:VTIMEZONE",
+            "garbage without a colon",
+            "END:VTIMEZONE",
+            "BEGIN:VTODO",
+            "SUMMARY:a",
+            "END:VTODO",
+            "END:VCALENDAR",
+        ].join("\r\n");
+        assert_eq!(parse(&text, now()).unwrap().len(), 1);
+        assert!(parse("BEGIN:VTODO\r\nnot a property\r\nEND:VTODO\r\n", now()).is_err());
+    }
+
+    #[test]
+    fn times_with_a_tzid_are_converted_from_that_zone() {
+        let text = "BEGIN:VTODO\r\nSUMMARY:a\r\nDUE;TZID=America/New_York:20240405T090000\r\nEND:VTODO\r\n";
+        let items = parse(text, now()).unwrap();
+        assert_eq!(items[0].due_at.unwrap(), chrono::DateTime::parse_from_rfc3339("2024-04-05T13:00:00Z").unwrap());
+
+        let unknown = text.replace("America/New_York", "Pacific Standard Time");
+        let error = parse(&unknown, now()).unwrap_err();
+        assert!(error.contains("unknown time zone 'Pacific Standard Time'"), "{}", error);
+    }
+}
@@ -1,6 +1,25 @@
 pub mod character;
 pub mod buffer;
+pub mod app;
 pub mod widget;
+pub mod container_widget;
+pub mod event_bus;
+pub mod error_boundary;
 pub mod dashboard_widget;
+pub mod todo;
+pub mod todo_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
 pub mod todo_widget;
+pub mod report;
+pub mod todo_txt;
+pub mod icalendar;
+pub mod taskwarrior;
+pub mod export;
+pub mod cli;
+pub mod text_view_widget;
+pub mod text_input;
+pub mod text_area;
 pub mod colours;
+#[cfg(test)]
+mod test_support;
@@ -0,0 +1,195 @@
+use crate::todo::{self, TodoItem, TodoItems};
+
+// An inclusive range of local dates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DateRange {
+    pub from: chrono::NaiveDate,
+    pub to: chrono::NaiveDate,
+}
+
+impl DateRange {
+    // Yesterday and today, what a standup looks back over.
+    pub fn standup(today: chrono::NaiveDate) -> DateRange {
+        return DateRange { from: today - chrono::TimeDelta::days(1), to: today };
+    }
+
+    pub fn contains(&self, at: chrono::DateTime<chrono::Utc>) -> bool {
+        let date = at.with_timezone(&chrono::Local).date_naive();
+        return self.from <= date && date <= self.to;
+    }
+
+    // What's typed into the TUI: empty for the standup range, one date for
+    // that day, "FROM..TO", or "7d" for the last seven days. Dates are read
+    // by `parse_date`.
+    pub fn parse(text: &str, today: chrono::NaiveDate) -> Result<DateRange, String> {
+        let text = text.trim();
+        let range = if text.len() == 0 {
+            DateRange::standup(today)
+        } else if let Some((from, to)) = text.split_once("..") {
+            DateRange { from: parse_date(from, today)?, to: parse_date(to, today)? }
+        } else if let Some(days) = text.strip_suffix('d').and_then(|days| days.parse::<u32>().ok()).filter(|days| *days > 0) {
+            let from = today.checked_sub_days(chrono::Days::new(days as u64 - 1)).ok_or(format!("can't go back {} days", days))?;
+            DateRange { from, to: today }
+        } else {
+            let date = parse_date(text, today)?;
+            DateRange { from: date, to: date }
+        };
+        if range.from > range.to {
+            return Err(format!("{} is after {}", range.from, range.to));
+        }
+        return Ok(range);
+    }
+
+    // Reads `--from DATE` and `--to DATE`, defaulting to the standup range.
+    // Dates are read by `parse_date`.
+    pub fn from_args(args: &[String], today: chrono::NaiveDate) -> Result<DateRange, String> {
+        let mut range = DateRange::standup(today);
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let date = match args.next() {
+                Some(value) => parse_date(value, today)?,
+                None => return Err(format!("{} needs a date", arg)),
+            };
+            match arg.as_str() {
+                "--from" => { range.from = date },
+                "--to" => { range.to = date },
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+        if range.from > range.to {
+            return Err(format!("{} is after {}", range.from, range.to));
+        }
+        return Ok(range);
+    }
+}
+
+// YYYY-MM-DD, "today" or "yesterday".
+pub fn parse_date(text: &str, today: chrono::NaiveDate) -> Result<chrono::NaiveDate, String> {
+    return match text.trim() {
+        "today" => Ok(today),
+        "yesterday" => Ok(today - chrono::TimeDelta::days(1)),
+        text => chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d").map_err(|_| format!("can't read '{}' as a date", text)),
+    };
+}
+
+fn bullet(item: &TodoItem, detail: String) -> String {
+    let mut line = format!("- {}", item.title);
+    if let Some(priority) = item.priority {
+        line = format!("- ({}) {}", priority, item.title);
+    }
+    for tag in &item.tags {
+        line.push_str(&format!(" #{}", tag));
+    }
+    return format!("{} ({})", line, detail);
+}
+
+fn section(title: &str, lines: Vec<String>) -> Vec<String> {
+    let mut section = vec![format!("## {}", title), "".to_string()];
+    if lines.len() == 0 {
+        section.push("_Nothing._".to_string());
+    } else {
+        section.extend(lines);
+    }
+    section.push("".to_string());
+    return section;
+}
+
+// A Markdown summary of what was finished and added in `range` and what's
+// being worked on at `now`.
+pub fn standup_report(items: &TodoItems, range: DateRange, now: chrono::DateTime<chrono::Utc>) -> String {
+    let mut completed: Vec<&TodoItem> = items.done_items.iter().filter(|item| item.completed_at.is_some_and(|at| range.contains(at))).collect();
+    completed.sort_by_key(|item| item.completed_at);
+    let completed_lines = completed.iter().map(|item| {
+        let mut detail = format!("took {}", todo::format_worked(item.completed_at.unwrap() - item.added_at));
+        if item.sessions.len() > 0 {
+            detail.push_str(&format!(", worked {}", todo::format_worked(item.time_worked(now))));
+        }
+        bullet(item, detail)
+    }).collect();
+
+    let in_progress_lines = items.todo_items.iter().filter(|item| item.working).map(|item| {
+        bullet(item, format!("worked {}", todo::format_worked(item.time_worked(now))))
+    }).collect();
+
+    let mut added: Vec<&TodoItem> = items.todo_items.iter().chain(items.done_items.iter()).filter(|item| range.contains(item.added_at)).collect();
+    added.sort_by_key(|item| item.added_at);
+    let added_lines = added.iter().map(|item| {
+        bullet(item, format!("added {}", item.added_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")))
+    }).collect();
+
+    let title = if range.from == range.to {
+        format!("# Standup {}", range.from)
+    } else {
+        format!("# Standup {} to {}", range.from, range.to)
+    };
+    let mut lines = vec![title, "".to_string()];
+    lines.extend(section("Completed", completed_lines));
+    lines.extend(section("In progress", in_progress_lines));
+    lines.extend(section("Added", added_lines));
+    return lines.join("\n");
+}
+
+#[cfg(test)]
+mod report_tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32) -> chrono::DateTime<chrono::Utc> {
+        return chrono::Local.with_ymd_and_hms(2024, 4, day, hour, 0, 0).unwrap().to_utc();
+    }
+
+    fn date(day: u32) -> chrono::NaiveDate {
+        return chrono::NaiveDate::from_ymd_opt(2024, 4, day).unwrap();
+    }
+
+    #[test]
+    fn lists_completed_in_progress_and_added_items_in_the_range() {
+        let mut finished = TodoItem::new("Write code".to_string(), at(1, 9));
+        finished.completed_at = Some(at(2, 11));
+        let mut old = TodoItem::new("Old thing".to_string(), at(1, 9));
+        old.completed_at = Some(at(1, 10));
+        let mut working = TodoItem::new("Feed dogs".to_string(), at(2, 8));
+        working.tags = vec!["home".to_string()];
+        working.start_work(at(3, 8));
+        let items = TodoItems::new(vec![finished, old, working]);
+
+        let report = standup_report(&items, DateRange::standup(date(3)), at(3, 9));
+        assert_eq!(report, [
+            "# Standup 2024-04-02 to 2024-04-03",
+            "",
+            "## Completed",
+            "",
+            "- Write code (took 26h00m)",
+            "",
+            "## In progress",
+            "",
+            "- Feed dogs #home (worked 1h00m)",
+            "",
+            "## Added",
+            "",
+            &format!("- Feed dogs #home (added {})", at(2, 8).with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")),
+            "",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn range_arguments() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(DateRange::from_args(&args(&[]), date(3)), Ok(DateRange { from: date(2), to: date(3) }));
+        assert_eq!(DateRange::from_args(&args(&["--from", "2024-04-01"]), date(3)), Ok(DateRange { from: date(1), to: date(3) }));
+        assert!(DateRange::from_args(&args(&["--to", "2024-03-01"]), date(3)).is_err());
+        assert!(DateRange::from_args(&args(&["--from"]), date(3)).is_err());
+        assert_eq!(DateRange::from_args(&args(&["--from", "yesterday", "--to", "today"]), date(3)), Ok(DateRange { from: date(2), to: date(3) }));
+    }
+
+    #[test]
+    fn typed_ranges() {
+        assert_eq!(DateRange::parse("", date(3)), Ok(DateRange::standup(date(3))));
+        assert_eq!(DateRange::parse("today", date(3)), Ok(DateRange { from: date(3), to: date(3) }));
+        assert_eq!(DateRange::parse("2024-04-01..yesterday", date(3)), Ok(DateRange { from: date(1), to: date(2) }));
+        assert_eq!(DateRange::parse("7d", date(10)), Ok(DateRange { from: date(4), to: date(10) }));
+        assert!(DateRange::parse("today..2024-04-01", date(3)).is_err());
+        assert!(DateRange::parse("0d", date(3)).is_err());
+        assert!(DateRange::parse("last week", date(3)).is_err());
+    }
+}
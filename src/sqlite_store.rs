@@ -0,0 +1,152 @@
+use std::path::PathBuf;
+use std::{fs, io};
+
+use rusqlite::{params, Connection};
+
+use crate::todo::{TodoItem, TodoItems};
+use crate::todo_store::{StorageError, TodoStore};
+
+// Each item is stored as its JSON representation, keyed by section and
+// position, so new item fields don't need a schema migration.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS items (
+        section TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (section, position)
+    )";
+
+const SECTIONS: [&str; 3] = ["todo", "done", "removed"];
+
+pub struct SqliteStore {
+    pub path: PathBuf,
+    connection: Option<Connection>,
+}
+
+// Only a damaged database or rows we can't read are corrupt. Anything else,
+// like the database being busy or locked, may go away if we try again.
+impl From<rusqlite::Error> for StorageError {
+    fn from(error: rusqlite::Error) -> StorageError {
+        return match &error {
+            rusqlite::Error::SqliteFailure(failure, _)
+                if matches!(failure.code, rusqlite::ErrorCode::DatabaseCorrupt | rusqlite::ErrorCode::NotADatabase) => {
+                StorageError::Corrupt(error.to_string())
+            },
+            rusqlite::Error::FromSqlConversionFailure(..) | rusqlite::Error::InvalidColumnType(..) => {
+                StorageError::Corrupt(error.to_string())
+            },
+            _ => StorageError::Io(io::Error::other(error)),
+        };
+    }
+}
+
+impl SqliteStore {
+    pub fn new(path: PathBuf) -> SqliteStore {
+        return SqliteStore { path, connection: None };
+    }
+
+    fn connection(&mut self) -> Result<&mut Connection, StorageError> {
+        if self.connection.is_none() {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let connection = Connection::open(&self.path)?;
+            connection.execute(SCHEMA, [])?;
+            self.connection = Some(connection);
+        }
+        return Ok(self.connection.as_mut().unwrap());
+    }
+
+    fn load_section(connection: &Connection, section: &str) -> Result<Vec<TodoItem>, StorageError> {
+        let mut statement = connection.prepare("SELECT data FROM items WHERE section = ?1 ORDER BY position")?;
+        let rows = statement.query_map(params![section], |row| row.get::<_, String>(0))?;
+        let mut items = vec![];
+        for row in rows {
+            let item = serde_json::from_str(&row?).map_err(|error| StorageError::Corrupt(error.to_string()))?;
+            items.push(item);
+        }
+        return Ok(items);
+    }
+}
+
+impl TodoStore for SqliteStore {
+    fn load(&mut self) -> Result<TodoItems, StorageError> {
+        let connection = self.connection()?;
+        let mut items = TodoItems::empty();
+        items.todo_items = SqliteStore::load_section(connection, SECTIONS[0])?;
+        items.done_items = SqliteStore::load_section(connection, SECTIONS[1])?;
+        items.removed_items = SqliteStore::load_section(connection, SECTIONS[2])?;
+        return Ok(items);
+    }
+
+    fn save(&mut self, items: &TodoItems) -> Result<(), StorageError> {
+        let connection = self.connection()?;
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM items", [])?;
+        let sections = [&items.todo_items, &items.done_items, &items.removed_items];
+        for (section, section_items) in SECTIONS.iter().zip(sections) {
+            for (position, item) in section_items.iter().enumerate() {
+                let data = serde_json::to_string(item).map_err(|error| StorageError::Corrupt(error.to_string()))?;
+                transaction.execute(
+                    "INSERT INTO items (section, position, data) VALUES (?1, ?2, ?3)",
+                    params![section, position as i64, data],
+                )?;
+            }
+        }
+        transaction.commit()?;
+        return Ok(());
+    }
+
+    fn append(&mut self, item: &TodoItem) -> Result<(), StorageError> {
+        let section = if item.completed_at == None { SECTIONS[0] } else { SECTIONS[1] };
+        let data = serde_json::to_string(item).map_err(|error| StorageError::Corrupt(error.to_string()))?;
+        let connection = self.connection()?;
+        connection.execute(
+            "INSERT INTO items (section, position, data)
+             VALUES (?1, (SELECT COALESCE(MAX(position), -1) + 1 FROM items WHERE section = ?1), ?2)",
+            params![section, data],
+        )?;
+        return Ok(());
+    }
+
+    fn describe(&self) -> String {
+        return self.path.display().to_string();
+    }
+}
+
+#[cfg(test)]
+mod sqlite_store_tests {
+    use super::*;
+    use crate::test_support::titles;
+
+    fn item(title: &str, completed: bool) -> TodoItem {
+        let added_at = chrono::DateTime::parse_from_rfc3339("2024-04-01T13:39:57Z").unwrap().to_utc();
+        let mut item = TodoItem::new(title.to_string(), added_at);
+        item.completed_at = if completed { Some(added_at) } else { None };
+        item.working = false;
+        return item;
+    }
+
+    #[test]
+    fn only_damaged_databases_are_corrupt() {
+        let failure = |code| rusqlite::Error::SqliteFailure(rusqlite::ffi::Error { code, extended_code: 0 }, None);
+        assert!(matches!(StorageError::from(failure(rusqlite::ErrorCode::DatabaseBusy)), StorageError::Io(_)));
+        assert!(matches!(StorageError::from(failure(rusqlite::ErrorCode::DatabaseLocked)), StorageError::Io(_)));
+        assert!(matches!(StorageError::from(failure(rusqlite::ErrorCode::NotADatabase)), StorageError::Corrupt(_)));
+        assert!(matches!(StorageError::from(failure(rusqlite::ErrorCode::DatabaseCorrupt)), StorageError::Corrupt(_)));
+    }
+
+    #[test]
+    fn save_append_and_load_keep_section_order() {
+        let path = std::env::temp_dir().join(format!("termod-test-{}-sqlite.db", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut store = SqliteStore::new(path);
+
+        store.save(&TodoItems::new(vec![item("a", false), item("b", true)])).unwrap();
+        store.append(&item("c", false)).unwrap();
+        let loaded = store.load().unwrap();
+
+        assert_eq!(titles(&loaded.todo_items), vec!["a", "c"]);
+        assert_eq!(titles(&loaded.done_items), vec!["b"]);
+    }
+}
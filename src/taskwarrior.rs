@@ -0,0 +1,247 @@
+// Reading the JSON written by `task export` and writing JSON `task import`
+// accepts. Pending tasks are open items, completed ones finished items and
+// deleted ones removed items.
+use serde_json::{Map, Value};
+
+use crate::todo::{self, TodoItem, TodoItems, WorkSession};
+
+// Attributes Taskwarrior works out for itself, which aren't worth keeping.
+const DERIVED: [&str; 4] = ["id", "urgency", "modified", "mask"];
+
+fn parse_date(value: &Value, name: &str) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    let text = value.as_str().ok_or_else(|| format!("{} isn't a string", name))?;
+    let naive = chrono::NaiveDateTime::parse_from_str(text, "%Y%m%dT%H%M%SZ").map_err(|_| format!("can't read {} '{}' as a date", name, text))?;
+    return Ok(naive.and_utc());
+}
+
+fn format_date(at: chrono::DateTime<chrono::Utc>) -> Value {
+    return Value::String(at.format("%Y%m%dT%H%M%SZ").to_string());
+}
+
+// Taskwarrior only has high, medium and low.
+fn priority_from_taskwarrior(priority: &str) -> Option<char> {
+    return match priority {
+        "H" => Some('A'),
+        "M" => Some('C'),
+        "L" => Some('E'),
+        _ => None,
+    };
+}
+
+fn priority_to_taskwarrior(priority: char) -> &'static str {
+    return match priority {
+        'A' | 'B' => "H",
+        'C' => "M",
+        _ => "L",
+    };
+}
+
+fn item_from_task(task: &Map<String, Value>, now: chrono::DateTime<chrono::Utc>) -> Result<TodoItem, String> {
+    let description = task.get("description").and_then(Value::as_str).ok_or("task without a description")?;
+    let mut item = TodoItem::new(description.to_string(), now);
+    for (key, value) in task {
+        match key.as_str() {
+            "description" | "status" => {},
+            "uuid" => { item.uuid = value.as_str().map(str::to_string) },
+            "entry" => { item.added_at = parse_date(value, key)? },
+            "end" => { item.completed_at = Some(parse_date(value, key)?) },
+            "due" => { item.due_at = Some(parse_date(value, key)?) },
+            "start" => {
+                item.working = true;
+                item.sessions.push(WorkSession { started_at: parse_date(value, key)?, ended_at: None });
+            },
+            "priority" => { item.priority = value.as_str().and_then(priority_from_taskwarrior) },
+            "tags" => {
+                let tags = value.as_array().ok_or("tags isn't a list")?;
+                item.tags = tags.iter().filter_map(Value::as_str).map(str::to_string).collect();
+            },
+            _ if DERIVED.contains(&key.as_str()) => {},
+            // Other attributes, like project or UDAs, are kept if they're
+            // plain values.
+            _ => match value {
+                Value::String(text) => item.extensions.push((key.clone(), text.clone())),
+                Value::Number(number) => item.extensions.push((key.clone(), number.to_string())),
+                _ => {},
+            },
+        }
+    }
+    return Ok(item);
+}
+
+pub fn parse(text: &str, now: chrono::DateTime<chrono::Utc>) -> Result<TodoItems, String> {
+    let tasks: Vec<Map<String, Value>> = serde_json::from_str(text).map_err(|error| error.to_string())?;
+    let mut items = TodoItems::empty();
+    for task in &tasks {
+        let mut item = item_from_task(task, now)?;
+        match task.get("status").and_then(Value::as_str).unwrap_or("pending") {
+            "completed" => {
+                item.working = false;
+                item.completed_at = item.completed_at.or(Some(now));
+                items.done_items.push(item);
+            },
+            "deleted" => {
+                item.working = false;
+                item.completed_at = None;
+                items.removed_items.push(item);
+            },
+            // The template recurring tasks are made from, not a task.
+            "recurring" => {},
+            _ => {
+                item.completed_at = None;
+                items.todo_items.push(item);
+            },
+        }
+    }
+    items.keep_one_working();
+    return Ok(items);
+}
+
+fn task_from_item(item: &TodoItem, status: &str) -> Value {
+    let mut task = Map::new();
+    let uuid = item.uuid.clone().unwrap_or_else(todo::new_uuid);
+    task.insert("uuid".to_string(), Value::String(uuid));
+    task.insert("description".to_string(), Value::String(item.title.clone()));
+    task.insert("status".to_string(), Value::String(status.to_string()));
+    task.insert("entry".to_string(), format_date(item.added_at));
+    if let Some(completed_at) = item.completed_at {
+        task.insert("end".to_string(), format_date(completed_at));
+    }
+    if let Some(due_at) = item.due_at {
+        task.insert("due".to_string(), format_date(due_at));
+    }
+    if let Some(session) = item.sessions.last().filter(|session| item.working && session.ended_at.is_none()) {
+        task.insert("start".to_string(), format_date(session.started_at));
+    }
+    if let Some(priority) = item.priority {
+        task.insert("priority".to_string(), Value::String(priority_to_taskwarrior(priority).to_string()));
+    }
+    if item.tags.len() > 0 {
+        task.insert("tags".to_string(), Value::Array(item.tags.iter().map(|tag| Value::String(tag.clone())).collect()));
+    }
+    for (key, value) in &item.extensions {
+        task.entry(key.clone()).or_insert_with(|| Value::String(value.clone()));
+    }
+    return Value::Object(task);
+}
+
+// Every section, removed items as deleted tasks.
+pub fn write(items: &TodoItems) -> String {
+    let tasks: Vec<Value> = items.todo_items.iter().map(|item| task_from_item(item, "pending"))
+        .chain(items.done_items.iter().map(|item| task_from_item(item, "completed")))
+        .chain(items.removed_items.iter().map(|item| task_from_item(item, "deleted")))
+        .collect();
+    return serde_json::to_string_pretty(&tasks).unwrap();
+}
+
+#[cfg(test)]
+mod taskwarrior_tests {
+    use super::*;
+    use crate::test_support::now;
+
+    const EXPORT: &str = r#"[
+        {"id": 1, "description": "Feed dogs", "entry": "20240401T090000Z", "modified": "20240401T090000Z",
+         "status": "pending", "uuid": "6d0bd6e7-2a3a-4d53-8a5b-7c3f3c0e4e11", "priority": "H", "tags": ["home"],
+         "due": "20240403T170000Z", "project": "chores", "urgency": 8.9},
+        {"id": 0, "description": "Write code", "entry": "20240401T090000Z", "end": "20240402T100000Z",
+         "status": "completed", "uuid": "0b8c2a54-51a2-4f6e-9f7d-2f0d7c1f5a22"},
+        {"id": 0, "description": "Old idea", "entry": "20240401T090000Z", "end": "20240402T100000Z",
+         "status": "deleted", "uuid": "9f5e8a1b-1c2d-4e3f-8a9b-0c1d2e3f4a33"}
+    ]"#;
+
+    #[test]
+    fn statuses_map_to_sections() {
+        let items = parse(EXPORT, now()).unwrap();
+        assert_eq!(items.todo_items.len(), 1);
+        assert_eq!(items.done_items.len(), 1);
+        assert_eq!(items.removed_items.len(), 1);
+
+        let pending = &items.todo_items[0];
+        assert_eq!(pending.title, "Feed dogs");
+        assert_eq!(pending.priority, Some('A'));
+        assert_eq!(pending.tags, vec!["home"]);
+        assert_eq!(pending.due_at.unwrap(), chrono::DateTime::parse_from_rfc3339("2024-04-03T17:00:00Z").unwrap());
+        assert_eq!(pending.extensions, vec![("project".to_string(), "chores".to_string())]);
+        assert_eq!(items.done_items[0].completed_at.unwrap(), chrono::DateTime::parse_from_rfc3339("2024-04-02T10:00:00Z").unwrap());
+        assert_eq!(items.removed_items[0].completed_at, None);
+    }
+
+    #[test]
+    fn uuids_survive_a_round_trip() {
+        let items = parse(EXPORT, now()).unwrap();
+        let again = parse(&write(&items), now()).unwrap();
+        assert_eq!(again.todo_items[0].uuid, items.todo_items[0].uuid);
+        assert_eq!(again.todo_items[0].extensions, items.todo_items[0].extensions);
+        assert_eq!(again.removed_items[0].uuid, items.removed_items[0].uuid);
+    }
+
+    #[test]
+    fn importing_again_updates_instead_of_duplicating() {
+        let mut items = parse(EXPORT, now()).unwrap();
+        let updated = EXPORT.replace(r#""status": "pending""#, r#""status": "completed", "end": "20240404T100000Z""#);
+        items.merge(parse(&updated, now()).unwrap());
+        assert_eq!(items.todo_items.len(), 0);
+        assert_eq!(items.done_items.len(), 2);
+        assert_eq!(items.removed_items.len(), 1);
+    }
+
+    #[test]
+    fn importing_again_keeps_what_taskwarrior_doesnt_have() {
+        let mut items = TodoItems::new(parse(EXPORT, now()).unwrap().todo_items);
+        items.add_child_to_selected_item("Buy food".to_string());
+        let feed = &mut items.todo_items[0];
+        feed.recurrence = Some(todo::Recurrence::Daily);
+        feed.collapsed = true;
+        feed.start_work(now());
+        feed.extensions.push(("todotxt-context".to_string(), "home".to_string()));
+
+        let updated = EXPORT.replace("Feed dogs", "Feed the dogs").replace(r#""project": "chores""#, r#""project": "pets""#);
+        items.merge(parse(&updated, now()).unwrap());
+        let feed = items.todo_items.iter().find(|item| item.title == "Feed the dogs").unwrap();
+        assert_eq!(feed.recurrence, Some(todo::Recurrence::Daily));
+        assert!(feed.collapsed);
+        assert!(feed.working);
+        assert_eq!(feed.extensions, vec![
+            ("project".to_string(), "pets".to_string()),
+            ("todotxt-context".to_string(), "home".to_string()),
+        ]);
+        assert_eq!(items.todo_items.iter().filter(|item| item.parent == Some(feed.id)).count(), 1);
+    }
+
+    #[test]
+    fn only_the_task_started_last_stays_started() {
+        let tasks = r#"[
+            {"description": "a", "status": "pending", "start": "20240401T090000Z"},
+            {"description": "b", "status": "pending", "start": "20240401T100000Z"}
+        ]"#;
+        let items = parse(tasks, now()).unwrap();
+        assert!(!items.todo_items[0].working);
+        assert_eq!(items.todo_items[0].sessions[0].ended_at, Some(items.todo_items[1].sessions[0].started_at));
+        assert!(items.todo_items[1].working);
+
+        let mut local = TodoItems::new(vec![TodoItem::new("c".to_string(), now())]);
+        local.toggle_selected_item_working();
+        local.merge(items);
+        assert_eq!(local.todo_items.iter().filter(|item| item.working).map(|item| item.title.as_str()).collect::<Vec<_>>(), vec!["c"]);
+    }
+
+    #[test]
+    fn items_get_their_own_uuid_when_added() {
+        // Same title and time, as undated todo.txt lines get.
+        let items = TodoItems::new(vec![TodoItem::new("a".to_string(), now()), TodoItem::new("a".to_string(), now())]);
+        let uuids: Vec<&String> = items.todo_items.iter().map(|item| item.uuid.as_ref().unwrap()).collect();
+        assert_ne!(uuids[0], uuids[1]);
+        assert_eq!(uuids[0].len(), 36);
+        assert_eq!(&uuids[0][14..15], "4");
+    }
+
+    #[test]
+    fn importing_an_export_adds_no_copies() {
+        let mut items = TodoItems::new(vec![TodoItem::new("a".to_string(), now()), TodoItem::new("a".to_string(), now())]);
+        items.highlighted_todo_item = 0;
+        items.add_child_to_selected_item("b".to_string());
+        let exported = write(&items);
+        items.merge(parse(&exported, now()).unwrap());
+        assert_eq!(items.todo_items.len(), 3);
+        assert_eq!(write(&items), exported);
+    }
+}
@@ -0,0 +1,62 @@
+// Fixtures shared by the tests of several modules.
+use crossterm::event;
+
+use crate::app::Navigation;
+use crate::buffer::Buffer;
+use crate::event_bus::AppEvent;
+use crate::todo::TodoItem;
+use crate::widget::{self, EventResult, Widget, WidgetTrait};
+
+// A fixed "now", so times in exports and ages come out the same every run.
+pub fn now() -> chrono::DateTime<chrono::Utc> {
+    return chrono::DateTime::parse_from_rfc3339("2024-04-05T12:00:00Z").unwrap().to_utc();
+}
+
+// The items' titles in stored order.
+pub fn titles(items: &[TodoItem]) -> Vec<&str> {
+    return items.iter().map(|item| item.title.as_str()).collect();
+}
+
+pub struct PanickyState;
+
+fn noop(_myself: &mut Widget<PanickyState>) {}
+fn panic_on_event(_myself: &mut Widget<PanickyState>, _event: &event::Event) -> EventResult {
+    panic!("boom");
+}
+fn empty_buffer(myself: &mut Widget<PanickyState>) -> Buffer {
+    return Buffer::new(myself.width, myself.height);
+}
+
+impl WidgetTrait for Widget<PanickyState> {
+    fn init(&mut self) {self.init()}
+    fn handle_event(&mut self, event_to_handle: &event::Event) -> EventResult {self.handle_event(event_to_handle)}
+    fn handle_app_event(&mut self, app_event: &AppEvent) {self.handle_app_event(app_event)}
+    fn take_published_events(&mut self) -> Vec<AppEvent> {self.take_published_events()}
+    fn take_navigation_requests(&mut self) -> Vec<Navigation> {self.take_navigation_requests()}
+    fn update(&mut self) {self.update()}
+    fn draw(&mut self) {self.draw()}
+    fn generate_buffer(&mut self) -> Buffer {self.generate_buffer()}
+    fn get_title(&self) -> &String {&self.title}
+    fn get_size(&self) -> (u16, u16) {(self.width, self.height)}
+    fn get_cursor_position(&self) -> Option<(u16, u16)> {self.cursor_position}
+}
+
+// A widget that panics on every event.
+pub fn panicky() -> Widget<PanickyState> {
+    return Widget {
+        width: 60,
+        height: 6,
+        title: "Panicky".to_string(),
+        init_fn: noop,
+        event_fn: panic_on_event,
+        app_event_fn: widget::ignore_app_event,
+        update_fn: noop,
+        draw_fn: noop,
+        generate_buffer_fn: empty_buffer,
+        subscriptions: vec![],
+        published_events: vec![],
+        navigation_requests: vec![],
+        cursor_position: None,
+        state: PanickyState,
+    };
+}
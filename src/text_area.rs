@@ -0,0 +1,411 @@
+use crate::buffer::Buffer;
+use crate::character::{self, Character};
+use crate::colours;
+use crate::widget::EventResult;
+use crossterm::event::{self, KeyCode, KeyModifiers};
+
+const UNDO_CAPACITY: usize = 100;
+
+// A position in the text: logical line and char index within it.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord)]
+pub struct TextPosition {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Clone)]
+struct Snapshot {
+    lines: Vec<Vec<char>>,
+    cursor: TextPosition,
+}
+
+// Multi-line editable text, soft wrapped to `width` and scrolled to keep the
+// cursor in view. Esc and Tab are left unhandled for the owning widget.
+pub struct TextArea {
+    pub width: u16,
+    pub height: u16,
+    lines: Vec<Vec<char>>,
+    cursor: TextPosition,
+    // The other end of the selection while Shift is held.
+    anchor: Option<TextPosition>,
+    // First visual row shown.
+    scroll: usize,
+    undo_stack: Vec<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+}
+
+impl TextArea {
+    pub fn new(width: u16, height: u16) -> TextArea {
+        return TextArea {
+            width,
+            height,
+            lines: vec![vec![]],
+            cursor: TextPosition { line: 0, column: 0 },
+            anchor: None,
+            scroll: 0,
+            undo_stack: vec![],
+            redo_stack: vec![],
+        };
+    }
+
+    pub fn text(&self) -> String {
+        return self.lines.iter().map(|line| line.iter().collect::<String>()).collect::<Vec<String>>().join("\n");
+    }
+
+    // Replaces the text, clearing the selection and history.
+    pub fn set_text(&mut self, text: &str) {
+        self.lines = text.split('\n').map(|line| line.chars().collect()).collect();
+        self.cursor = TextPosition { line: 0, column: 0 };
+        self.anchor = None;
+        self.scroll = 0;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    pub fn cursor(&self) -> TextPosition {
+        return self.cursor;
+    }
+
+    // The selected range, start first.
+    pub fn selection(&self) -> Option<(TextPosition, TextPosition)> {
+        let anchor = self.anchor?;
+        if anchor == self.cursor {
+            return None;
+        }
+        return Some((anchor.min(self.cursor), anchor.max(self.cursor)));
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        let mut text = String::new();
+        for line in start.line..=end.line {
+            let from = if line == start.line { start.column } else { 0 };
+            let to = if line == end.line { end.column } else { self.lines[line].len() };
+            text.extend(&self.lines[line][from..to]);
+            if line != end.line {
+                text.push('\n');
+            }
+        }
+        return Some(text);
+    }
+
+    pub fn handle_event(&mut self, event_to_handle: &event::Event) -> EventResult {
+        let key = match event_to_handle {
+            event::Event::Key(key) => key,
+            _ => return EventResult::Unhandled,
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+
+        match key.code {
+            KeyCode::Char('z') if ctrl => { self.undo() },
+            KeyCode::Char('y') if ctrl => { self.redo() },
+            KeyCode::Char(c) => { self.insert_text(&c.to_string()) },
+            KeyCode::Enter => { self.insert_text("\n") },
+            KeyCode::Backspace => { self.delete_backward() },
+            KeyCode::Delete => { self.delete_forward() },
+            KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down | KeyCode::Home | KeyCode::End => {
+                if shift {
+                    if self.anchor.is_none() {
+                        self.anchor = Some(self.cursor);
+                    }
+                } else {
+                    self.anchor = None;
+                }
+                self.move_cursor(key.code);
+            },
+            _ => { return EventResult::Unhandled }
+        }
+        self.scroll_to_cursor();
+        return EventResult::Consumed;
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(snapshot) = self.undo_stack.pop() {
+            self.redo_stack.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(snapshot) = self.redo_stack.pop() {
+            self.undo_stack.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    // The cursor's position within the rendered buffer, if it's in view.
+    pub fn cursor_position(&self) -> Option<(u16, u16)> {
+        let (row, column) = self.visual_position(self.cursor);
+        if row < self.scroll || row >= self.scroll + self.height as usize {
+            return None;
+        }
+        return Some((column as u16, (row - self.scroll) as u16));
+    }
+
+    pub fn render(&self) -> Buffer {
+        let mut buffer = Buffer::new(self.width, self.height);
+        let selection = self.selection();
+        let rows = self.visual_rows();
+        for (y, (line, start)) in rows.iter().skip(self.scroll).take(self.height as usize).enumerate() {
+            let end = (start + self.wrap_width()).min(self.lines[*line].len());
+            for column in *start..end {
+                let mut character = Character {
+                    c: self.lines[*line][column],
+                    attributes: character::empty_attr_set(),
+                    colour: colours::LIGHT_GREY,
+                };
+                let position = TextPosition { line: *line, column };
+                if let Some((selection_start, selection_end)) = selection {
+                    if selection_start <= position && position < selection_end {
+                        character.highlight();
+                    }
+                }
+                buffer[y * self.width as usize + column - start] = character;
+            }
+        }
+        return buffer;
+    }
+
+    fn wrap_width(&self) -> usize {
+        return (self.width as usize).max(1);
+    }
+
+    // (line, first column) of every visual row. A line that exactly fills its
+    // last row gets an empty row after it for the cursor to sit on.
+    fn visual_rows(&self) -> Vec<(usize, usize)> {
+        let mut rows = vec![];
+        for (line, chars) in self.lines.iter().enumerate() {
+            let mut start = 0;
+            loop {
+                rows.push((line, start));
+                start += self.wrap_width();
+                if start > chars.len() {
+                    break;
+                }
+            }
+        }
+        return rows;
+    }
+
+    fn visual_position(&self, position: TextPosition) -> (usize, usize) {
+        let width = self.wrap_width();
+        let rows_before: usize = self.lines[..position.line].iter().map(|line| line.len() / width + 1).sum();
+        return (rows_before + position.column / width, position.column % width);
+    }
+
+    fn position_at_visual(&self, row: usize, column: usize) -> TextPosition {
+        let rows = self.visual_rows();
+        let (line, start) = rows[row.min(rows.len() - 1)];
+        let line_len = self.lines[line].len();
+        return TextPosition { line, column: (start + column).min(line_len) };
+    }
+
+    fn move_cursor(&mut self, code: KeyCode) {
+        let line_len = self.lines[self.cursor.line].len();
+        match code {
+            KeyCode::Left => {
+                if self.cursor.column > 0 {
+                    self.cursor.column -= 1;
+                } else if self.cursor.line > 0 {
+                    self.cursor.line -= 1;
+                    self.cursor.column = self.lines[self.cursor.line].len();
+                }
+            },
+            KeyCode::Right => {
+                if self.cursor.column < line_len {
+                    self.cursor.column += 1;
+                } else if self.cursor.line + 1 < self.lines.len() {
+                    self.cursor.line += 1;
+                    self.cursor.column = 0;
+                }
+            },
+            KeyCode::Up => {
+                let (row, column) = self.visual_position(self.cursor);
+                if row > 0 {
+                    self.cursor = self.position_at_visual(row - 1, column);
+                }
+            },
+            KeyCode::Down => {
+                let (row, column) = self.visual_position(self.cursor);
+                if row + 1 < self.visual_rows().len() {
+                    self.cursor = self.position_at_visual(row + 1, column);
+                }
+            },
+            KeyCode::Home => { self.cursor.column = 0 },
+            KeyCode::End => { self.cursor.column = line_len },
+            _ => {}
+        }
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        self.push_undo();
+        self.delete_selection();
+        for c in text.chars() {
+            if c == '\n' {
+                let rest = self.lines[self.cursor.line].split_off(self.cursor.column);
+                self.lines.insert(self.cursor.line + 1, rest);
+                self.cursor = TextPosition { line: self.cursor.line + 1, column: 0 };
+            } else {
+                self.lines[self.cursor.line].insert(self.cursor.column, c);
+                self.cursor.column += 1;
+            }
+        }
+    }
+
+    fn delete_backward(&mut self) {
+        if self.selection().is_some() {
+            self.push_undo();
+            self.delete_selection();
+        } else if self.cursor.column > 0 {
+            self.push_undo();
+            self.cursor.column -= 1;
+            self.lines[self.cursor.line].remove(self.cursor.column);
+        } else if self.cursor.line > 0 {
+            // Join with the line above.
+            self.push_undo();
+            let line = self.lines.remove(self.cursor.line);
+            self.cursor.line -= 1;
+            self.cursor.column = self.lines[self.cursor.line].len();
+            self.lines[self.cursor.line].extend(line);
+        }
+        self.anchor = None;
+    }
+
+    fn delete_forward(&mut self) {
+        if self.selection().is_some() {
+            self.push_undo();
+            self.delete_selection();
+        } else if self.cursor.column < self.lines[self.cursor.line].len() {
+            self.push_undo();
+            self.lines[self.cursor.line].remove(self.cursor.column);
+        } else if self.cursor.line + 1 < self.lines.len() {
+            // Join with the line below.
+            self.push_undo();
+            let line = self.lines.remove(self.cursor.line + 1);
+            self.lines[self.cursor.line].extend(line);
+        }
+        self.anchor = None;
+    }
+
+    fn delete_selection(&mut self) {
+        if let Some((start, end)) = self.selection() {
+            let tail = self.lines[end.line].split_off(end.column);
+            self.lines[start.line].truncate(start.column);
+            self.lines[start.line].extend(tail);
+            self.lines.drain(start.line + 1..=end.line);
+            self.cursor = start;
+        }
+        self.anchor = None;
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        return Snapshot { lines: self.lines.clone(), cursor: self.cursor };
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.lines = snapshot.lines;
+        self.cursor = snapshot.cursor;
+        self.anchor = None;
+        self.scroll_to_cursor();
+    }
+
+    fn push_undo(&mut self) {
+        if self.undo_stack.len() == UNDO_CAPACITY {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(self.snapshot());
+        self.redo_stack.clear();
+    }
+
+    fn scroll_to_cursor(&mut self) {
+        let (row, _) = self.visual_position(self.cursor);
+        let height = (self.height as usize).max(1);
+        if row < self.scroll {
+            self.scroll = row;
+        } else if row >= self.scroll + height {
+            self.scroll = row + 1 - height;
+        }
+    }
+}
+
+#[cfg(test)]
+mod text_area_tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> event::Event {
+        return event::Event::Key(event::KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    fn with_modifiers(code: KeyCode, modifiers: KeyModifiers) -> event::Event {
+        return event::Event::Key(event::KeyEvent::new(code, modifiers));
+    }
+
+    fn type_text(area: &mut TextArea, text: &str) {
+        for c in text.chars() {
+            if c == '\n' {
+                area.handle_event(&key(KeyCode::Enter));
+            } else {
+                area.handle_event(&key(KeyCode::Char(c)));
+            }
+        }
+    }
+
+    fn row(buffer: &Buffer, y: usize) -> String {
+        return (0..buffer.width as usize).map(|x| buffer[y * buffer.width as usize + x].c).collect();
+    }
+
+    #[test]
+    fn enter_splits_and_backspace_joins_lines() {
+        let mut area = TextArea::new(10, 3);
+        type_text(&mut area, "abcd");
+        area.handle_event(&key(KeyCode::Left));
+        area.handle_event(&key(KeyCode::Left));
+        area.handle_event(&key(KeyCode::Enter));
+        assert_eq!(area.text(), "ab\ncd");
+
+        area.handle_event(&key(KeyCode::Backspace));
+        assert_eq!(area.text(), "abcd");
+        assert_eq!(area.cursor(), TextPosition { line: 0, column: 2 });
+    }
+
+    #[test]
+    fn long_lines_soft_wrap_and_scroll() {
+        let mut area = TextArea::new(4, 2);
+        type_text(&mut area, "abcdefghij");
+        let buffer = area.render();
+
+        assert_eq!(row(&buffer, 0), "efgh");
+        assert_eq!(row(&buffer, 1), "ij  ");
+        assert_eq!(area.cursor_position(), Some((2, 1)));
+
+        area.handle_event(&key(KeyCode::Up));
+        area.handle_event(&key(KeyCode::Up));
+        assert_eq!(area.cursor(), TextPosition { line: 0, column: 2 });
+        assert_eq!(row(&area.render(), 0), "abcd");
+    }
+
+    #[test]
+    fn shift_arrows_select_and_typing_replaces_the_selection() {
+        let mut area = TextArea::new(10, 3);
+        type_text(&mut area, "one\ntwo");
+        area.handle_event(&with_modifiers(KeyCode::Up, KeyModifiers::SHIFT));
+        area.handle_event(&with_modifiers(KeyCode::Left, KeyModifiers::SHIFT));
+        assert_eq!(area.selected_text(), Some("e\ntwo".to_string()));
+
+        area.handle_event(&key(KeyCode::Char('!')));
+        assert_eq!(area.text(), "on!");
+    }
+
+    #[test]
+    fn undo_and_redo_step_through_edits() {
+        let mut area = TextArea::new(10, 3);
+        type_text(&mut area, "ab");
+        area.handle_event(&with_modifiers(KeyCode::Char('z'), KeyModifiers::CONTROL));
+        assert_eq!(area.text(), "a");
+        area.handle_event(&with_modifiers(KeyCode::Char('z'), KeyModifiers::CONTROL));
+        assert_eq!(area.text(), "");
+        area.handle_event(&with_modifiers(KeyCode::Char('y'), KeyModifiers::CONTROL));
+        assert_eq!(area.text(), "a");
+    }
+}
@@ -0,0 +1,318 @@
+use crate::buffer::Buffer;
+use crate::character::Character;
+use crate::colours;
+use crate::widget::EventResult;
+use crossterm::event::{self, KeyCode, KeyModifiers};
+
+const HISTORY_CAPACITY: usize = 50;
+
+// A single line of editable text, rendered into a one row buffer. Enter and
+// Esc are left unhandled so the owning widget decides what they mean.
+pub struct TextInput {
+    pub width: u16,
+    pub placeholder: String,
+    pub max_length: Option<usize>,
+    text: Vec<char>,
+    cursor: usize,
+    scroll: usize,
+    history: Vec<String>,
+    history_index: Option<usize>,
+    // What was being typed before stepping into the history.
+    draft: String,
+}
+
+fn is_word_char(c: char) -> bool {
+    return !c.is_whitespace();
+}
+
+impl TextInput {
+    pub fn new(width: u16) -> TextInput {
+        return TextInput {
+            width,
+            placeholder: "".to_string(),
+            max_length: None,
+            text: vec![],
+            cursor: 0,
+            scroll: 0,
+            history: vec![],
+            history_index: None,
+            draft: "".to_string(),
+        };
+    }
+
+    pub fn with_placeholder(mut self, placeholder: &str) -> TextInput {
+        self.placeholder = placeholder.to_string();
+        return self;
+    }
+
+    pub fn with_max_length(mut self, max_length: usize) -> TextInput {
+        self.max_length = Some(max_length);
+        return self;
+    }
+
+    pub fn text(&self) -> String {
+        return self.text.iter().collect();
+    }
+
+    pub fn cursor(&self) -> usize {
+        return self.cursor;
+    }
+
+    // Replaces the text and puts the cursor at the end.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.chars().collect();
+        if let Some(max_length) = self.max_length {
+            self.text.truncate(max_length);
+        }
+        self.cursor = self.text.len();
+        self.history_index = None;
+        self.scroll_to_cursor();
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text("");
+    }
+
+    // Returns the text, remembers it in the history and clears the input.
+    pub fn submit(&mut self) -> String {
+        let text = self.text();
+        if text.len() > 0 && self.history.last() != Some(&text) {
+            if self.history.len() == HISTORY_CAPACITY {
+                self.history.remove(0);
+            }
+            self.history.push(text.clone());
+        }
+        self.clear();
+        return text;
+    }
+
+    pub fn handle_event(&mut self, event_to_handle: &event::Event) -> EventResult {
+        let key = match event_to_handle {
+            event::Event::Key(key) => key,
+            _ => return EventResult::Unhandled,
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+
+        match key.code {
+            KeyCode::Char('w') if ctrl => { self.delete_word_before() },
+            KeyCode::Char('a') if ctrl => { self.cursor = 0 },
+            KeyCode::Char('e') if ctrl => { self.cursor = self.text.len() },
+            // Other Ctrl and Alt combinations aren't text.
+            KeyCode::Char(c) if !ctrl && !alt => { self.insert(c) },
+            KeyCode::Backspace if ctrl => { self.delete_word_before() },
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.text.remove(self.cursor);
+            },
+            KeyCode::Backspace => {},
+            KeyCode::Delete if ctrl => { self.delete_word_after() },
+            KeyCode::Delete if self.cursor < self.text.len() => {
+                self.text.remove(self.cursor);
+            },
+            KeyCode::Delete => {},
+            KeyCode::Left if ctrl => { self.cursor = self.word_start_before() },
+            KeyCode::Right if ctrl => { self.cursor = self.word_end_after() },
+            KeyCode::Left => { self.cursor = self.cursor.saturating_sub(1) },
+            KeyCode::Right => { self.cursor = (self.cursor + 1).min(self.text.len()) },
+            KeyCode::Home => { self.cursor = 0 },
+            KeyCode::End => { self.cursor = self.text.len() },
+            KeyCode::Up => { self.history_prev() },
+            KeyCode::Down => { self.history_next() },
+            _ => { return EventResult::Unhandled }
+        }
+        self.scroll_to_cursor();
+        return EventResult::Consumed;
+    }
+
+    // The cursor's column within the rendered buffer.
+    pub fn cursor_column(&self) -> u16 {
+        return (self.cursor - self.scroll) as u16;
+    }
+
+    pub fn render(&self) -> Buffer {
+        let mut buffer = Buffer::new(self.width, 1);
+        let chars = if self.text.len() == 0 {
+            Character::vec_from_string(&self.placeholder, Some(colours::GREY), None)
+        } else {
+            let visible: String = self.text.iter().skip(self.scroll).collect();
+            Character::vec_from_string(&visible, None, None)
+        };
+        let visible_len = chars.len().min(self.width as usize);
+        buffer.insert_char_slice(0, &chars[..visible_len]);
+        return buffer;
+    }
+
+    fn insert(&mut self, c: char) {
+        if let Some(max_length) = self.max_length {
+            if self.text.len() >= max_length {
+                return;
+            }
+        }
+        self.text.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn word_start_before(&self) -> usize {
+        let mut i = self.cursor;
+        while i > 0 && !is_word_char(self.text[i - 1]) {
+            i -= 1;
+        }
+        while i > 0 && is_word_char(self.text[i - 1]) {
+            i -= 1;
+        }
+        return i;
+    }
+
+    fn word_end_after(&self) -> usize {
+        let mut i = self.cursor;
+        while i < self.text.len() && !is_word_char(self.text[i]) {
+            i += 1;
+        }
+        while i < self.text.len() && is_word_char(self.text[i]) {
+            i += 1;
+        }
+        return i;
+    }
+
+    fn delete_word_before(&mut self) {
+        let start = self.word_start_before();
+        self.text.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    fn delete_word_after(&mut self) {
+        let end = self.word_end_after();
+        self.text.drain(self.cursor..end);
+    }
+
+    fn history_prev(&mut self) {
+        if self.history.len() == 0 {
+            return;
+        }
+        let index = match self.history_index {
+            None => {
+                self.draft = self.text();
+                self.history.len() - 1
+            },
+            Some(index) => index.saturating_sub(1),
+        };
+        self.history_index = Some(index);
+        self.text = self.history[index].chars().collect();
+        self.cursor = self.text.len();
+    }
+
+    fn history_next(&mut self) {
+        let index = match self.history_index {
+            Some(index) => index + 1,
+            None => return,
+        };
+        if index < self.history.len() {
+            self.history_index = Some(index);
+            self.text = self.history[index].chars().collect();
+        } else {
+            self.history_index = None;
+            self.text = self.draft.chars().collect();
+        }
+        self.cursor = self.text.len();
+    }
+
+    fn scroll_to_cursor(&mut self) {
+        let width = (self.width as usize).max(1);
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + width {
+            self.scroll = self.cursor + 1 - width;
+        }
+    }
+}
+
+#[cfg(test)]
+mod text_input_tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> event::Event {
+        return event::Event::Key(event::KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    fn ctrl(code: KeyCode) -> event::Event {
+        return event::Event::Key(event::KeyEvent::new(code, KeyModifiers::CONTROL));
+    }
+
+    fn type_text(input: &mut TextInput, text: &str) {
+        for c in text.chars() {
+            input.handle_event(&key(KeyCode::Char(c)));
+        }
+    }
+
+    #[test]
+    fn word_jumps_and_delete_word() {
+        let mut input = TextInput::new(40);
+        type_text(&mut input, "buy more dog food");
+        input.handle_event(&ctrl(KeyCode::Left));
+        input.handle_event(&ctrl(KeyCode::Left));
+        assert_eq!(input.cursor(), 9);
+        input.handle_event(&ctrl(KeyCode::Right));
+        assert_eq!(input.cursor(), 12);
+        input.handle_event(&ctrl(KeyCode::Char('w')));
+        assert_eq!(input.text(), "buy more  food");
+    }
+
+    #[test]
+    fn scrolls_to_keep_the_cursor_visible() {
+        let mut input = TextInput::new(5);
+        type_text(&mut input, "abcdefgh");
+        assert_eq!(input.cursor_column(), 4);
+        let rendered: String = (0..5).map(|i| input.render()[i].c).collect();
+        assert_eq!(rendered, "efgh ");
+
+        input.handle_event(&key(KeyCode::Home));
+        assert_eq!(input.cursor_column(), 0);
+        let rendered: String = (0..5).map(|i| input.render()[i].c).collect();
+        assert_eq!(rendered, "abcde");
+    }
+
+    #[test]
+    fn max_length_and_placeholder() {
+        let mut input = TextInput::new(10).with_max_length(3).with_placeholder("Title");
+        assert_eq!(input.render()[0].c, 'T');
+        type_text(&mut input, "abcdef");
+        assert_eq!(input.text(), "abc");
+    }
+
+    #[test]
+    fn history_steps_back_through_submissions_and_restores_the_draft() {
+        let mut input = TextInput::new(10);
+        type_text(&mut input, "one");
+        input.submit();
+        type_text(&mut input, "two");
+        input.submit();
+        type_text(&mut input, "dr");
+
+        input.handle_event(&key(KeyCode::Up));
+        assert_eq!(input.text(), "two");
+        input.handle_event(&key(KeyCode::Up));
+        assert_eq!(input.text(), "one");
+        input.handle_event(&key(KeyCode::Down));
+        input.handle_event(&key(KeyCode::Down));
+        assert_eq!(input.text(), "dr");
+    }
+
+    #[test]
+    fn enter_and_esc_are_left_to_the_owner() {
+        let mut input = TextInput::new(10);
+        assert_eq!(input.handle_event(&key(KeyCode::Enter)), EventResult::Unhandled);
+        assert_eq!(input.handle_event(&key(KeyCode::Esc)), EventResult::Unhandled);
+    }
+
+    #[test]
+    fn unbound_ctrl_and_alt_keys_are_not_typed() {
+        let mut input = TextInput::new(10);
+        type_text(&mut input, "ab");
+        assert_eq!(input.handle_event(&ctrl(KeyCode::Char('x'))), EventResult::Unhandled);
+        let alt_x = event::Event::Key(event::KeyEvent::new(KeyCode::Char('x'), KeyModifiers::ALT));
+        assert_eq!(input.handle_event(&alt_x), EventResult::Unhandled);
+        assert_eq!(input.handle_event(&key(KeyCode::Char('X'))), EventResult::Consumed);
+        assert_eq!(input.text(), "abX");
+    }
+}
@@ -0,0 +1,98 @@
+use crate::app::Navigation;
+use crate::buffer::Buffer;
+use crate::character::Character;
+use crate::colours;
+use crate::event_bus::AppEvent;
+use crate::widget::{self, EventResult, Widget, WidgetTrait};
+use crossterm::event;
+
+// A bordered, scrollable block of read-only text. Used for detail pages and
+// help screens pushed onto the view stack.
+pub struct TextViewState {
+    pub lines: Vec<String>,
+    pub scroll: usize,
+    buffer: Buffer,
+}
+
+impl WidgetTrait for Widget<TextViewState> {
+    fn init(&mut self) {self.init()}
+    fn handle_event(&mut self, event_to_handle: &event::Event) -> EventResult {self.handle_event(event_to_handle)}
+    fn handle_app_event(&mut self, app_event: &AppEvent) {self.handle_app_event(app_event)}
+    fn take_published_events(&mut self) -> Vec<AppEvent> {self.take_published_events()}
+    fn take_navigation_requests(&mut self) -> Vec<Navigation> {self.take_navigation_requests()}
+    fn update(&mut self) {self.update()}
+    fn draw(&mut self) {self.draw()}
+    fn generate_buffer(&mut self) -> Buffer {self.generate_buffer()}
+    fn get_title(&self) -> &String {&self.title}
+    fn get_size(&self) -> (u16, u16) {(self.width, self.height)}
+    fn get_cursor_position(&self) -> Option<(u16, u16)> {self.cursor_position}
+}
+
+fn init(_myself: &mut Widget<TextViewState>) {}
+
+fn event(myself: &mut Widget<TextViewState>, event_to_handle: &event::Event) -> EventResult {
+    match event_to_handle {
+        event::Event::Key(event) => {
+            match event.code {
+                event::KeyCode::Up => {
+                    myself.state.scroll = myself.state.scroll.saturating_sub(1);
+                },
+                event::KeyCode::Down => {
+                    if myself.state.scroll + 1 < myself.state.lines.len() {
+                        myself.state.scroll += 1;
+                    }
+                },
+                event::KeyCode::Char('q') => { myself.pop_view() },
+                _ => { return EventResult::Unhandled }
+            }
+            return EventResult::Consumed;
+        },
+        _ => { return EventResult::Unhandled }
+    }
+}
+
+fn update(_myself: &mut Widget<TextViewState>) {}
+
+fn draw(myself: &mut Widget<TextViewState>) {
+    let inner_width = (myself.width as usize).saturating_sub(2);
+    let inner_height = (myself.height as usize).saturating_sub(2);
+    myself.state.buffer.clear();
+    widget::add_buffer_border(&mut myself.state.buffer, colours::LIGHT_GREY);
+
+    let title: String = myself.title.chars().take(inner_width.saturating_sub(2)).collect();
+    let title_chars = Character::vec_from_string(&title, None, None);
+    myself.state.buffer.insert_char_slice(2, &title_chars);
+
+    for (row, line) in myself.state.lines.iter().skip(myself.state.scroll).take(inner_height).enumerate() {
+        let text: String = line.chars().take(inner_width).collect();
+        let line_chars = Character::vec_from_string(&text, None, None);
+        myself.state.buffer.insert_char_slice((row + 1) * myself.width as usize + 1, &line_chars);
+    }
+}
+
+fn generate_buffer(myself: &mut Widget<TextViewState>) -> Buffer {
+    return myself.state.buffer.clone();
+}
+
+pub fn new(width: u16, height: u16, title: &str, lines: Vec<String>) -> Widget<TextViewState> {
+    return Widget {
+        width,
+        height,
+        title: title.to_string(),
+        init_fn: init,
+        event_fn: event,
+        app_event_fn: widget::ignore_app_event,
+        update_fn: update,
+        draw_fn: draw,
+        generate_buffer_fn: generate_buffer,
+        subscriptions: vec![],
+        published_events: vec![],
+        navigation_requests: vec![],
+        cursor_position: None,
+        state: TextViewState {
+            lines,
+            scroll: 0,
+            buffer: Buffer::new(width, height),
+        }
+    };
+}
@@ -0,0 +1,2044 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Section {
+    TODO, 
+    DONE,
+}
+
+pub const PRIORITIES: [char; 5] = ['A', 'B', 'C', 'D', 'E'];
+
+// How close to its due date an item has to be to count as due soon.
+pub const DUE_SOON: chrono::TimeDelta = chrono::TimeDelta::hours(24);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DueState {
+    Overdue,
+    DueSoon,
+    Later,
+}
+
+// A stretch of time spent working on an item. The last session of a working
+// item is still open.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorkSession {
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub ended_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// How a recurring item comes back once it's completed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Recurrence {
+    Daily,
+    // On each of these days. With none given, a week after the last one.
+    Weekly(Vec<chrono::Weekday>),
+    Monthly,
+    // This many days after the last one was completed, e.g. changing a filter.
+    AfterCompletion(u32),
+}
+
+// The longest gap "every Nd" takes, about a hundred years.
+const MAX_EVERY_DAYS: u32 = 36500;
+
+const WEEKDAYS: [(&str, chrono::Weekday); 7] = [
+    ("mon", chrono::Weekday::Mon),
+    ("tue", chrono::Weekday::Tue),
+    ("wed", chrono::Weekday::Wed),
+    ("thu", chrono::Weekday::Thu),
+    ("fri", chrono::Weekday::Fri),
+    ("sat", chrono::Weekday::Sat),
+    ("sun", chrono::Weekday::Sun),
+];
+
+impl Recurrence {
+    // "daily", "weekly", "weekly mon,thu", "monthly" or "every 3d". Empty
+    // text stops an item recurring.
+    pub fn parse(text: &str) -> Result<Option<Recurrence>, String> {
+        let text = text.trim().to_lowercase();
+        let (rule, rest) = text.split_once(char::is_whitespace).unwrap_or((&text, ""));
+        return match (rule, rest.trim()) {
+            ("", _) => Ok(None),
+            ("daily", "") => Ok(Some(Recurrence::Daily)),
+            ("monthly", "") => Ok(Some(Recurrence::Monthly)),
+            ("weekly", days) => {
+                let mut weekdays = vec![];
+                for day in days.split(|c: char| c.is_whitespace() || c == ',').filter(|day| day.len() > 0) {
+                    match WEEKDAYS.iter().find(|(name, _)| day.starts_with(name)) {
+                        Some((_, weekday)) if !weekdays.contains(weekday) => weekdays.push(*weekday),
+                        Some(_) => {},
+                        None => return Err(format!("can't read '{}' as a day of the week", day)),
+                    }
+                }
+                weekdays.sort_by_key(|weekday| weekday.num_days_from_monday());
+                Ok(Some(Recurrence::Weekly(weekdays)))
+            },
+            ("every", days) => {
+                let (amount, unit) = days.split_at(days.find(|c: char| !c.is_ascii_digit()).unwrap_or(days.len()));
+                match (amount.parse::<u32>(), unit.trim()) {
+                    (Ok(days), "" | "d" | "day" | "days") if days > MAX_EVERY_DAYS => Err(format!("every Nd can't be more than {} days", MAX_EVERY_DAYS)),
+                    (Ok(days), "" | "d" | "day" | "days") if days > 0 => Ok(Some(Recurrence::AfterCompletion(days))),
+                    _ => Err(format!("can't read '{}', use every Nd", text)),
+                }
+            },
+            _ => Err(format!("can't read '{}', use daily, weekly [days], monthly or every Nd", text)),
+        };
+    }
+
+    // The same form `parse` reads.
+    pub fn describe(&self) -> String {
+        return match self {
+            Recurrence::Daily => "daily".to_string(),
+            Recurrence::Weekly(weekdays) if weekdays.len() == 0 => "weekly".to_string(),
+            Recurrence::Weekly(weekdays) => {
+                let names: Vec<&str> = weekdays.iter()
+                    .filter_map(|weekday| WEEKDAYS.iter().find(|(_, w)| w == weekday).map(|(name, _)| *name))
+                    .collect();
+                format!("weekly {}", names.join(","))
+            },
+            Recurrence::Monthly => "monthly".to_string(),
+            Recurrence::AfterCompletion(days) => format!("every {}d", days),
+        };
+    }
+
+    // When the occurrence after one due at `due_at` is due. Calendar rules
+    // skip occurrences that had already passed when it was completed, so an
+    // item finished late doesn't come back overdue. None if that's past the
+    // last date chrono can hold.
+    pub fn next_due(&self, due_at: chrono::DateTime<chrono::Utc>, completed_at: chrono::DateTime<chrono::Utc>) -> Option<chrono::DateTime<chrono::Utc>> {
+        let due = due_at.with_timezone(&chrono::Local).naive_local();
+        let completed = completed_at.with_timezone(&chrono::Local).naive_local();
+        let utc = |naive: chrono::NaiveDateTime| -> chrono::DateTime<chrono::Utc> {
+            return match naive.and_local_timezone(chrono::Local).earliest() {
+                Some(local) => local.to_utc(),
+                None => naive.and_utc(),
+            };
+        };
+        if let Recurrence::AfterCompletion(days) = self {
+            let date = completed.date().checked_add_days(chrono::Days::new(*days as u64))?;
+            return Some(utc(date.and_time(due.time())));
+        }
+        let day = chrono::TimeDelta::days(1);
+        let mut next = due;
+        for step in 1.. {
+            next = match self {
+                Recurrence::Daily => due.checked_add_signed(chrono::TimeDelta::try_days(step)?)?,
+                Recurrence::Weekly(weekdays) if weekdays.len() == 0 => due.checked_add_signed(chrono::TimeDelta::try_weeks(step)?)?,
+                Recurrence::Weekly(weekdays) => {
+                    let mut next_day = next.checked_add_signed(day)?;
+                    while !weekdays.contains(&chrono::Datelike::weekday(&next_day)) {
+                        next_day = next_day.checked_add_signed(day)?;
+                    }
+                    next_day
+                },
+                _ => due.checked_add_months(chrono::Months::new(u32::try_from(step).ok()?))?,
+            };
+            if next > completed {
+                break;
+            }
+        }
+        return Some(utc(next));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TodoItem {
+    pub title: String,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub added_at: chrono::DateTime<chrono::Utc>,
+    pub working: bool,
+    #[serde(default)]
+    pub priority: Option<char>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub due_at: Option<chrono::DateTime<chrono::Utc>>,
+    // 0 until the item is given one, which happens when it's added or first
+    // gets children.
+    #[serde(default)]
+    pub id: u64,
+    #[serde(default)]
+    pub parent: Option<u64>,
+    #[serde(default)]
+    pub sessions: Vec<WorkSession>,
+    // key:value pairs from imported files that termod has no field for,
+    // written back out on export.
+    #[serde(default)]
+    pub extensions: Vec<(String, String)>,
+    // Set on items imported from tools that identify tasks by UUID, so
+    // importing them again updates them instead of adding copies.
+    #[serde(default)]
+    pub uuid: Option<String>,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    // Whether the item's subtasks are hidden.
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+impl TodoItem {
+    pub fn new(title: String, added_at: chrono::DateTime<chrono::Utc>) -> TodoItem {
+        return TodoItem {
+            title,
+            completed_at: None,
+            added_at,
+            working: false,
+            priority: None,
+            tags: vec![],
+            due_at: None,
+            id: 0,
+            parent: None,
+            sessions: vec![],
+            extensions: vec![],
+            uuid: None,
+            recurrence: None,
+            collapsed: false,
+        };
+    }
+
+    pub fn start_work(&mut self, now: chrono::DateTime<chrono::Utc>) {
+        if !self.working {
+            self.working = true;
+            self.sessions.push(WorkSession { started_at: now, ended_at: None });
+        }
+    }
+
+    pub fn stop_work(&mut self, now: chrono::DateTime<chrono::Utc>) {
+        self.working = false;
+        for session in self.sessions.iter_mut().filter(|session| session.ended_at.is_none()) {
+            session.ended_at = Some(now);
+        }
+    }
+
+    // When the open work session started, if there is one.
+    fn working_since(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        return self.sessions.iter().filter(|session| session.ended_at.is_none()).map(|session| session.started_at).max();
+    }
+
+    // Takes what an import says about this item. Fields the import left empty
+    // are kept, as the format may not have them, and so is local state like
+    // collapsing.
+    fn update_from(&mut self, imported: TodoItem) {
+        self.title = imported.title;
+        self.added_at = imported.added_at;
+        self.completed_at = imported.completed_at;
+        self.working = (self.working || imported.working) && self.completed_at.is_none();
+        if imported.priority.is_some() {
+            self.priority = imported.priority;
+        }
+        if imported.tags.len() > 0 {
+            self.tags = imported.tags;
+        }
+        if imported.due_at.is_some() {
+            self.due_at = imported.due_at;
+        }
+        if imported.recurrence.is_some() {
+            self.recurrence = imported.recurrence;
+        }
+        if imported.sessions.len() > 0 {
+            self.sessions = imported.sessions;
+        }
+        for (key, value) in imported.extensions {
+            match self.extensions.iter_mut().find(|(existing, _)| *existing == key) {
+                Some(extension) => { extension.1 = value },
+                None => { self.extensions.push((key, value)) },
+            }
+        }
+    }
+
+    // Total over all sessions, counting an open session up to `now`.
+    pub fn time_worked(&self, now: chrono::DateTime<chrono::Utc>) -> chrono::TimeDelta {
+        return self.sessions.iter()
+            .map(|session| session.ended_at.unwrap_or(now) - session.started_at)
+            .fold(chrono::TimeDelta::zero(), |total, worked| total + worked);
+    }
+
+    // The copy of a recurring item that replaces it once it's completed. Items
+    // without a due date are treated as due at the end of the day they were
+    // completed.
+    pub fn next_occurrence(&self, completed_at: chrono::DateTime<chrono::Utc>) -> Option<TodoItem> {
+        let recurrence = self.recurrence.as_ref()?;
+        let due_at = match self.due_at {
+            Some(due_at) => due_at,
+            None => parse_due("today", completed_at.with_timezone(&chrono::Local)).ok()??,
+        };
+        let mut next = TodoItem::new(self.title.clone(), completed_at);
+        next.priority = self.priority;
+        next.tags = self.tags.clone();
+        next.due_at = Some(recurrence.next_due(due_at, completed_at)?);
+        next.parent = self.parent;
+        next.recurrence = Some(recurrence.clone());
+        return Some(next);
+    }
+
+    pub fn calculate_age(&self) -> chrono::TimeDelta {
+        return chrono::Utc::now() - self.added_at;
+    }
+
+    // Open items only; finished items are never overdue.
+    pub fn due_state(&self, now: chrono::DateTime<chrono::Utc>) -> Option<DueState> {
+        let due_at = match (self.due_at, self.completed_at) {
+            (Some(due_at), None) => due_at,
+            _ => return None,
+        };
+        if due_at < now {
+            return Some(DueState::Overdue);
+        } else if due_at - now <= DUE_SOON {
+            return Some(DueState::DueSoon);
+        }
+        return Some(DueState::Later);
+    }
+
+    // None -> A -> ... -> E -> None
+    pub fn next_priority(&self) -> Option<char> {
+        return match self.priority {
+            None => Some(PRIORITIES[0]),
+            Some(priority) => PRIORITIES.iter().position(|p| *p == priority)
+                .and_then(|index| PRIORITIES.get(index + 1))
+                .copied(),
+        };
+    }
+
+    pub fn detail_lines(&self) -> Vec<String> {
+        let completed_at = match self.completed_at {
+            Some(completed_at) => completed_at.to_rfc3339(),
+            None => "-".to_string(),
+        };
+        let due_at = match self.due_at {
+            Some(due_at) => due_at.to_rfc3339(),
+            None => "-".to_string(),
+        };
+        return vec![
+            format!("Title:     {}", self.title),
+            format!("Priority:  {}", self.priority.map(String::from).unwrap_or("-".to_string())),
+            format!("Tags:      {}", if self.tags.len() > 0 { self.tags.join(" ") } else { "-".to_string() }),
+            format!("Due:       {}", due_at),
+            format!("Repeats:   {}", self.recurrence.as_ref().map(|recurrence| recurrence.describe()).unwrap_or("-".to_string())),
+            format!("Added:     {}", self.added_at.to_rfc3339()),
+            format!("Completed: {}", completed_at),
+            format!("Working:   {}", if self.working { "yes" } else { "no" }),
+            format!("Worked:    {} in {} sessions", format_worked(self.time_worked(chrono::Utc::now())), self.sessions.len()),
+        ];
+    }
+}
+
+// A random version 4 UUID, with the RFC 4122 variant bits.
+pub fn new_uuid() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    return format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32]);
+}
+
+// Splits on whitespace and commas, dropping a leading '#' and duplicates.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    for tag in text.split(|c: char| c.is_whitespace() || c == ',') {
+        let tag = tag.trim_start_matches('#');
+        if tag.len() > 0 && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    return tags;
+}
+
+// Reads a due date typed in local time. Accepts "YYYY-MM-DD", "YYYY-MM-DD HH:MM",
+// "today", "tomorrow", "+Nd" and "+Nh". Dates without a time are due at the
+// end of the day. An empty string clears the due date.
+pub fn parse_due(text: &str, now: chrono::DateTime<chrono::Local>) -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+    let text = text.trim();
+    let end_of_day = chrono::NaiveTime::from_hms_opt(23, 59, 0).unwrap();
+    let local = |naive: chrono::NaiveDateTime| -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+        return match naive.and_local_timezone(chrono::Local).earliest() {
+            Some(local) => Ok(Some(local.to_utc())),
+            None => Err(format!("{} doesn't exist in the local time zone", naive)),
+        };
+    };
+
+    if text.len() == 0 {
+        return Ok(None);
+    } else if text == "today" {
+        return local(now.date_naive().and_time(end_of_day));
+    } else if text == "tomorrow" {
+        return local((now.date_naive() + chrono::TimeDelta::days(1)).and_time(end_of_day));
+    } else if let Some(offset) = text.strip_prefix('+') {
+        let (amount, to_delta): (&str, fn(i64) -> Option<chrono::TimeDelta>) = if let Some(days) = offset.strip_suffix('d') {
+            (days, chrono::TimeDelta::try_days)
+        } else if let Some(hours) = offset.strip_suffix('h') {
+            (hours, chrono::TimeDelta::try_hours)
+        } else {
+            return Err(format!("can't read '{}' as an offset, use +Nd or +Nh", text));
+        };
+        let amount: i64 = amount.parse().map_err(|_| format!("can't read '{}' as an offset", text))?;
+        return match to_delta(amount).and_then(|delta| now.checked_add_signed(delta)) {
+            Some(due_at) => Ok(Some(due_at.to_utc())),
+            None => Err("offset out of range".to_string()),
+        };
+    } else if let Ok(naive) = chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M") {
+        return local(naive);
+    } else if let Ok(date) = chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return local(date.and_time(end_of_day));
+    }
+    return Err(format!("can't read '{}' as a due date", text));
+}
+
+// e.g. "2h05m", "12m" or "40s".
+pub fn format_worked(worked: chrono::TimeDelta) -> String {
+    if worked.num_hours() > 0 {
+        return format!("{}h{:0>2}m", worked.num_hours(), worked.num_minutes() % 60);
+    } else if worked.num_minutes() > 0 {
+        return format!("{}m", worked.num_minutes());
+    }
+    return format!("{}s", worked.num_seconds().max(0));
+}
+
+// Short form used in item rows, e.g. "due 04-02 18:00".
+pub fn format_due(due_at: chrono::DateTime<chrono::Utc>) -> String {
+    return format!("due {}", due_at.with_timezone(&chrono::Local).format("%m-%d %H:%M"));
+}
+
+// How a section is ordered on screen. `Manual` is the stored order; the
+// others only change what's shown, never the stored order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortMode {
+    Manual,
+    Age,
+    Title,
+    Priority,
+    Due,
+    Completed,
+}
+
+impl SortMode {
+    pub fn next(self) -> SortMode {
+        return match self {
+            SortMode::Manual => SortMode::Age,
+            SortMode::Age => SortMode::Title,
+            SortMode::Title => SortMode::Priority,
+            SortMode::Priority => SortMode::Due,
+            SortMode::Due => SortMode::Completed,
+            SortMode::Completed => SortMode::Manual,
+        };
+    }
+
+    pub fn name(self) -> &'static str {
+        return match self {
+            SortMode::Manual => "manual",
+            SortMode::Age => "age",
+            SortMode::Title => "title",
+            SortMode::Priority => "priority",
+            SortMode::Due => "due",
+            SortMode::Completed => "completed",
+        };
+    }
+
+    // Oldest, A to Z, A before E, soonest due, most recently completed.
+    // Items without a priority, due date or completion go last.
+    fn compare(self, a: &TodoItem, b: &TodoItem) -> std::cmp::Ordering {
+        fn missing_last<T: Ord>(a: Option<T>, b: Option<T>) -> std::cmp::Ordering {
+            return match (a, b) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            };
+        }
+        return match self {
+            SortMode::Manual => std::cmp::Ordering::Equal,
+            SortMode::Age => a.added_at.cmp(&b.added_at),
+            SortMode::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            SortMode::Priority => missing_last(a.priority, b.priority),
+            SortMode::Due => missing_last(a.due_at, b.due_at),
+            SortMode::Completed => missing_last(a.completed_at.map(std::cmp::Reverse), b.completed_at.map(std::cmp::Reverse)),
+        };
+    }
+}
+
+// Which items are shown. An item has to pass every filter that's set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    pub tag: Option<String>,
+    pub search: Option<String>,
+    pub overdue: bool,
+    pub working: bool,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        return *self == Filter::default();
+    }
+
+    pub fn matches(&self, item: &TodoItem, now: chrono::DateTime<chrono::Utc>) -> bool {
+        if let Some(tag) = &self.tag {
+            if !item.tags.iter().any(|t| t == tag) {
+                return false;
+            }
+        }
+        if let Some(search) = &self.search {
+            if !item_matches_search(item, search) {
+                return false;
+            }
+        }
+        if self.overdue && item.due_state(now) != Some(DueState::Overdue) {
+            return false;
+        }
+        if self.working && !item.working {
+            return false;
+        }
+        return true;
+    }
+
+    // e.g. "#home overdue working"
+    pub fn describe(&self) -> String {
+        let mut parts: Vec<String> = vec![];
+        if let Some(tag) = &self.tag {
+            parts.push(format!("#{}", tag));
+        }
+        if let Some(search) = &self.search {
+            parts.push(format!("/{}", search));
+        }
+        if self.overdue {
+            parts.push("overdue".to_string());
+        }
+        if self.working {
+            parts.push("working".to_string());
+        }
+        return parts.join(" ");
+    }
+}
+
+// Finds `pattern`'s characters in order in `text`, ignoring case and spaces
+// in the pattern. Returns the positions of the matched characters in `text`.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Vec<usize>> {
+    let mut pattern_chars = pattern.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).peekable();
+    let mut positions: Vec<usize> = vec![];
+    for (position, c) in text.chars().enumerate() {
+        match pattern_chars.peek() {
+            Some(wanted) if c.to_lowercase().eq(std::iter::once(*wanted)) => {
+                positions.push(position);
+                pattern_chars.next();
+            },
+            Some(_) => {},
+            None => break,
+        }
+    }
+    if pattern_chars.peek().is_some() {
+        return None;
+    }
+    return Some(positions);
+}
+
+pub fn item_matches_search(item: &TodoItem, search: &str) -> bool {
+    return fuzzy_match(search, &item.title).is_some() || item.tags.iter().any(|tag| fuzzy_match(search, tag).is_some());
+}
+
+// Where an item sits. `Absent` is used for items that are being created.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Slot {
+    Todo(usize),
+    Done(usize),
+    Removed(usize),
+    Absent,
+}
+
+// One item moving between slots, with its state on either side. Undoing a
+// change moves `before` back to `from`.
+#[derive(Clone, Debug)]
+struct Change {
+    from: Slot,
+    to: Slot,
+    before: Option<TodoItem>,
+    after: Option<TodoItem>,
+}
+
+// Every change an action made, undone and redone together.
+type HistoryEntry = Vec<Change>;
+
+pub struct TodoItems {
+    pub active_section: Section,
+    pub todo_items: Vec<TodoItem>,
+    pub done_items: Vec<TodoItem>,
+    pub removed_items: Vec<TodoItem>,
+    pub highlighted_todo_item: usize,
+    pub highlighted_done_item: usize,
+    pub todo_sort: SortMode,
+    pub done_sort: SortMode,
+    pub filter: Filter,
+    // Ids of items marked for a bulk action.
+    pub marked: HashSet<u64>,
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+}
+
+impl TodoItems {
+    pub fn new(items: Vec<TodoItem>) -> TodoItems {
+        let mut todo_items: Vec<TodoItem> = vec![];
+        let mut done_items: Vec<TodoItem>= vec![];
+        for item in items {
+            if item.completed_at == None {
+                todo_items.push(item);
+            } else {
+                done_items.push(item);
+            }
+        }
+
+        let mut items = TodoItems {
+            active_section: Section::TODO,
+            todo_items,
+            done_items,
+            removed_items: vec![],
+            highlighted_todo_item: 0,
+            highlighted_done_item: 0,
+            todo_sort: SortMode::Manual,
+            done_sort: SortMode::Manual,
+            filter: Filter::default(),
+            marked: HashSet::new(),
+            undo_stack: vec![],
+            redo_stack: vec![],
+        };
+        items.assign_missing_ids();
+        return items;
+    }
+
+    pub fn empty() -> TodoItems {
+        return TodoItems {
+            active_section: Section::TODO,
+            todo_items: vec![],
+            done_items: vec![],
+            removed_items: vec![],
+            highlighted_todo_item: 0,
+            highlighted_done_item: 0,
+            todo_sort: SortMode::Manual,
+            done_sort: SortMode::Manual,
+            filter: Filter::default(),
+            marked: HashSet::new(),
+            undo_stack: vec![],
+            redo_stack: vec![],
+        }
+    }
+
+    pub fn selected_item(&self) -> Option<&TodoItem> {
+        return self.item_at(self.selected_slot());
+    }
+
+    // Hidden items can't be selected, so this is `Absent` when the filter
+    // hides everything in the active section.
+    pub fn selected_slot(&self) -> Slot {
+        let index = self.highlighted(self.active_section);
+        if self.visible(self.active_section).contains(&index) {
+            return section_slot(self.active_section, index);
+        }
+        return Slot::Absent;
+    }
+
+    pub fn section_items(&self, section: Section) -> &Vec<TodoItem> {
+        return if section == Section::TODO { &self.todo_items } else { &self.done_items };
+    }
+
+    pub fn highlighted(&self, section: Section) -> usize {
+        return if section == Section::TODO { self.highlighted_todo_item } else { self.highlighted_done_item };
+    }
+
+    fn set_highlighted(&mut self, section: Section, index: usize) {
+        if section == Section::TODO {
+            self.highlighted_todo_item = index;
+        } else {
+            self.highlighted_done_item = index;
+        }
+    }
+
+    pub fn sort_mode(&self, section: Section) -> SortMode {
+        return if section == Section::TODO { self.todo_sort } else { self.done_sort };
+    }
+
+    pub fn cycle_sort_mode(&mut self, section: Section) {
+        if section == Section::TODO {
+            self.todo_sort = self.todo_sort.next();
+        } else {
+            self.done_sort = self.done_sort.next();
+        }
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+        self.snap_highlights();
+    }
+
+    // Indices into the section's items, filtered and in display order.
+    pub fn visible(&self, section: Section) -> Vec<usize> {
+        return self.visible_tree(section).into_iter().map(|(index, _)| index).collect();
+    }
+
+    // Like `visible`, with each item's depth in the tree. Items whose parent
+    // is in the other section are shown at the top level. Parents of items
+    // the filter lets through are shown too, so matches keep their context.
+    // Collapsed items hide their children, except while searching.
+    pub fn visible_tree(&self, section: Section) -> Vec<(usize, usize)> {
+        return self.tree_rows(section, &self.filter, self.sort_mode(section), true);
+    }
+
+    // Every item in the section in stored order, with its depth, ignoring
+    // the filter, sort mode and collapsed items.
+    pub fn full_tree(&self, section: Section) -> Vec<(usize, usize)> {
+        return self.tree_rows(section, &Filter::default(), SortMode::Manual, false);
+    }
+
+    fn tree_rows(&self, section: Section, filter: &Filter, sort: SortMode, collapse: bool) -> Vec<(usize, usize)> {
+        let items = self.section_items(section);
+        let now = chrono::Utc::now();
+        let index_of_id: HashMap<u64, usize> = items.iter().enumerate()
+            .filter(|(_, item)| item.id != 0)
+            .map(|(index, item)| (item.id, index))
+            .collect();
+        let parent_index = |index: usize| items[index].parent.and_then(|parent| index_of_id.get(&parent).copied());
+
+        let mut shown = vec![false; items.len()];
+        for index in 0..items.len() {
+            if filter.matches(&items[index], now) {
+                let mut current = Some(index);
+                while let Some(i) = current.filter(|i| !shown[*i]) {
+                    shown[i] = true;
+                    current = parent_index(i);
+                }
+            }
+        }
+
+        let mut children: HashMap<Option<usize>, Vec<usize>> = HashMap::new();
+        for index in (0..items.len()).filter(|i| shown[*i]) {
+            children.entry(parent_index(index)).or_default().push(index);
+        }
+        for siblings in children.values_mut() {
+            // Stable, so ties keep the stored order.
+            siblings.sort_by(|a, b| sort.compare(&items[*a], &items[*b]));
+        }
+
+        let expand_all = !collapse || filter.search.is_some();
+        let mut rows: Vec<(usize, usize)> = vec![];
+        let mut stack: Vec<(usize, usize)> = children.get(&None).map(|roots| roots.iter().rev().map(|i| (*i, 0)).collect()).unwrap_or_default();
+        while let Some((index, depth)) = stack.pop() {
+            rows.push((index, depth));
+            if expand_all || !items[index].collapsed {
+                if let Some(kids) = children.get(&Some(index)) {
+                    stack.extend(kids.iter().rev().map(|i| (*i, depth + 1)));
+                }
+            }
+        }
+        return rows;
+    }
+
+    pub fn has_children(&self, item: &TodoItem) -> bool {
+        return item.id != 0 && self.todo_items.iter().chain(self.done_items.iter()).any(|other| other.parent == Some(item.id));
+    }
+
+    // (done, total) over the item's direct children, if it has any.
+    pub fn progress(&self, item: &TodoItem) -> Option<(usize, usize)> {
+        if item.id == 0 {
+            return None;
+        }
+        let total = self.todo_items.iter().chain(self.done_items.iter()).filter(|other| other.parent == Some(item.id)).count();
+        let done = self.done_items.iter().filter(|other| other.parent == Some(item.id)).count();
+        return if total > 0 { Some((done, total)) } else { None };
+    }
+
+    // Collapsing only changes what's shown, so it isn't part of the history.
+    pub fn toggle_selected_collapsed(&mut self) {
+        let slot = match self.selected_item() {
+            Some(item) if self.has_children(item) => self.selected_slot(),
+            _ => return,
+        };
+        match slot {
+            Slot::Todo(index) => { self.todo_items[index].collapsed = !self.todo_items[index].collapsed },
+            Slot::Done(index) => { self.done_items[index].collapsed = !self.done_items[index].collapsed },
+            _ => {},
+        }
+    }
+
+    // Marks the highlighted item, or unmarks it if it's already marked.
+    pub fn toggle_selected_marked(&mut self) {
+        if let Some(id) = self.selected_id() {
+            if !self.marked.remove(&id) {
+                self.marked.insert(id);
+            }
+        }
+    }
+
+    pub fn mark_selected_item(&mut self) {
+        if let Some(id) = self.selected_id() {
+            self.marked.insert(id);
+        }
+    }
+
+    pub fn is_marked(&self, item: &TodoItem) -> bool {
+        return item.id != 0 && self.marked.contains(&item.id);
+    }
+
+    // Marked items in stored order, open ones first.
+    pub fn marked_items(&self) -> Vec<&TodoItem> {
+        return self.todo_items.iter().chain(self.done_items.iter()).filter(|item| self.is_marked(item)).collect();
+    }
+
+    // The highlighted item's id. Marks refer to items by id so they follow
+    // them as they move.
+    fn selected_id(&self) -> Option<u64> {
+        return self.item_at(self.selected_slot()).map(|item| item.id);
+    }
+
+    // Ids of the item's children, their children and so on, parents first.
+    pub fn descendants(&self, id: u64) -> Vec<u64> {
+        let mut descendants: Vec<u64> = vec![];
+        if id == 0 {
+            return descendants;
+        }
+        let mut stack = vec![id];
+        while let Some(parent) = stack.pop() {
+            for item in self.todo_items.iter().chain(self.done_items.iter()) {
+                if item.parent == Some(parent) && item.id != 0 && !descendants.contains(&item.id) {
+                    descendants.push(item.id);
+                    stack.push(item.id);
+                }
+            }
+        }
+        return descendants;
+    }
+
+    pub fn slot_of(&self, id: u64) -> Slot {
+        if let Some(index) = self.todo_items.iter().position(|item| item.id == id) {
+            return Slot::Todo(index);
+        } else if let Some(index) = self.done_items.iter().position(|item| item.id == id) {
+            return Slot::Done(index);
+        }
+        return Slot::Absent;
+    }
+
+    // Adds imported items to their sections. Items with the UUID of one that's
+    // already here update it, keeping its place in the tree and whatever the
+    // import didn't have.
+    pub fn merge(&mut self, imported: TodoItems) {
+        let sections = [(Section::TODO, imported.todo_items), (Section::DONE, imported.done_items)];
+        let sections = sections.into_iter().map(|(section, items)| (Some(section), items)).chain(std::iter::once((None, imported.removed_items)));
+        for (section, items) in sections {
+            for mut item in items {
+                // Ids from the import mean nothing here. New items get their
+                // own and start at the top level.
+                item.id = 0;
+                item.parent = None;
+                if let Some(uuid) = item.uuid.clone() {
+                    for existing_items in [&mut self.todo_items, &mut self.done_items, &mut self.removed_items] {
+                        if let Some(index) = existing_items.iter().position(|existing| existing.uuid.as_ref() == Some(&uuid)) {
+                            let mut existing = existing_items.remove(index);
+                            existing.update_from(item);
+                            item = existing;
+                            break;
+                        }
+                    }
+                }
+                let item = self.identified(item);
+                match section {
+                    Some(Section::TODO) => self.todo_items.push(item),
+                    Some(Section::DONE) => self.done_items.push(item),
+                    None => self.removed_items.push(item),
+                }
+            }
+        }
+        self.keep_one_working();
+        self.clamp_highlights();
+    }
+
+    // Only one item is worked on at a time. Where several are, e.g. after an
+    // import, the one started last stays and the others stop when it started.
+    pub fn keep_one_working(&mut self) {
+        let latest = self.todo_items.iter().enumerate()
+            .filter(|(_, item)| item.working)
+            .max_by_key(|(_, item)| item.working_since());
+        let (keep, since) = match latest {
+            Some((index, item)) => (index, item.working_since().unwrap_or_else(chrono::Utc::now)),
+            None => return,
+        };
+        for (index, item) in self.todo_items.iter_mut().enumerate() {
+            if index != keep && item.working {
+                item.stop_work(since);
+            }
+        }
+    }
+
+    // Gives every item without an id or UUID one, e.g. items from files
+    // written before they had them. Returns whether anything changed, so the
+    // new ones can be saved before they're used.
+    pub fn assign_missing_ids(&mut self) -> bool {
+        let mut next_id = self.next_id();
+        let mut changed = false;
+        for item in self.todo_items.iter_mut().chain(self.done_items.iter_mut()).chain(self.removed_items.iter_mut()) {
+            if item.id == 0 {
+                item.id = next_id;
+                next_id += 1;
+                changed = true;
+            }
+            if item.uuid.is_none() {
+                item.uuid = Some(new_uuid());
+                changed = true;
+            }
+        }
+        return changed;
+    }
+
+    // The item with an id and UUID, if it was missing either.
+    fn identified(&self, mut item: TodoItem) -> TodoItem {
+        if item.id == 0 {
+            item.id = self.next_id();
+        }
+        if item.uuid.is_none() {
+            item.uuid = Some(new_uuid());
+        }
+        return item;
+    }
+
+    fn next_id(&self) -> u64 {
+        let items = self.todo_items.iter().chain(self.done_items.iter()).chain(self.removed_items.iter());
+        return items.map(|item| item.id).max().unwrap_or(0) + 1;
+    }
+
+    pub fn item_at(&self, slot: Slot) -> Option<&TodoItem> {
+        return match slot {
+            Slot::Todo(index) => self.todo_items.get(index),
+            Slot::Done(index) => self.done_items.get(index),
+            Slot::Removed(index) => self.removed_items.get(index),
+            Slot::Absent => None,
+        };
+    }
+
+    // Adds a new todo to the end of the TODO section and highlights it.
+    pub fn add_item(&mut self, title: String) {
+        let item = self.identified(TodoItem::new(title, chrono::Utc::now()));
+        self.execute(vec![Change { from: Slot::Absent, to: Slot::Todo(self.todo_items.len()), before: None, after: Some(item) }]);
+        self.highlighted_todo_item = self.todo_items.len() - 1;
+        self.snap_highlights();
+    }
+
+    // Adds a subtask under the selected open item, expands it and highlights
+    // the new subtask.
+    pub fn add_child_to_selected_item(&mut self, title: String) {
+        let slot = self.selected_slot();
+        let parent = match (slot, self.item_at(slot)) {
+            (Slot::Todo(_), Some(parent)) => parent.clone(),
+            _ => return,
+        };
+        let positions = self.remember_positions();
+        let mut entry: HistoryEntry = vec![];
+        let parent_id = parent.id;
+        let mut child = self.identified(TodoItem::new(title, chrono::Utc::now()));
+        child.parent = Some(parent_id);
+        self.step(&mut entry, Change { from: Slot::Absent, to: Slot::Todo(self.todo_items.len()), before: None, after: Some(child) });
+        self.commit(entry, positions);
+        if let Slot::Todo(index) = self.slot_of(parent_id) {
+            self.todo_items[index].collapsed = false;
+        }
+        self.highlighted_todo_item = self.todo_items.len() - 1;
+        self.snap_highlights();
+    }
+
+    // Adds an item to the end of the section its completion puts it in.
+    pub fn push_item(&mut self, item: TodoItem) {
+        let item = self.identified(item);
+        if item.completed_at == None {
+            self.todo_items.push(item);
+        } else {
+            self.done_items.push(item);
+        }
+    }
+
+    pub fn next_item(&mut self) {
+        let visible = self.visible(self.active_section);
+        match visible.iter().position(|i| *i == self.highlighted(self.active_section)) {
+            Some(position) if position + 1 < visible.len() => self.set_highlighted(self.active_section, visible[position + 1]),
+            Some(_) => {},
+            None if visible.len() > 0 => self.set_highlighted(self.active_section, visible[0]),
+            None => {},
+        }
+    }
+
+    pub fn prev_item(&mut self) {
+        let visible = self.visible(self.active_section);
+        match visible.iter().position(|i| *i == self.highlighted(self.active_section)) {
+            Some(position) if position > 0 => self.set_highlighted(self.active_section, visible[position - 1]),
+            Some(_) => {},
+            None if visible.len() > 0 => self.set_highlighted(self.active_section, visible[0]),
+            None => {},
+        }
+    }
+
+    pub fn move_selected_item_up(&mut self) -> Result<(), String> {
+        return self.move_selected_item(true);
+    }
+
+    pub fn move_selected_item_down(&mut self) -> Result<(), String> {
+        return self.move_selected_item(false);
+    }
+
+    // Swaps the highlighted item with the sibling shown next to it, taking its
+    // subtasks along, and keeps it highlighted. Only the stored order changes,
+    // so a section sorted any other way than manually is an error.
+    fn move_selected_item(&mut self, up: bool) -> Result<(), String> {
+        let section = self.active_section;
+        let sort = self.sort_mode(section);
+        if sort != SortMode::Manual {
+            return Err(format!("sorted by {}, change sort to manual to move items", sort.name()));
+        }
+        let index = self.highlighted(section);
+        if self.item_at(section_slot(section, index)).is_none() {
+            return Ok(());
+        }
+        let rows = self.visible_tree(section);
+        let position = match rows.iter().position(|(i, _)| *i == index) {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+        let depth = rows[position].1;
+        let others: Vec<&(usize, usize)> = if up {
+            rows[..position].iter().rev().collect()
+        } else {
+            rows[position + 1..].iter().collect()
+        };
+        let sibling = others.into_iter()
+            .take_while(|(_, other_depth)| *other_depth >= depth)
+            .find(|(_, other_depth)| *other_depth == depth);
+        if let Some((target, _)) = sibling {
+            let item = self.section_items(section)[index].clone();
+            let (from, to) = (section_slot(section, index), section_slot(section, *target));
+            self.execute(vec![Change { from, to, before: Some(item.clone()), after: Some(item) }]);
+            self.set_highlighted(section, *target);
+        }
+        return Ok(());
+    }
+
+    pub fn toggle_section(&mut self) {
+        if self.active_section == Section::TODO {
+            self.active_section = Section::DONE;
+        } else {
+            self.active_section = Section::TODO;
+        }
+    }
+
+    // Removes the selected item along with all of its subtasks, as one step.
+    pub fn remove_selected_item(&mut self) {
+        let from = self.selected_slot();
+        let item = match self.item_at(from) {
+            Some(item) => item.clone(),
+            None => return,
+        };
+        let positions = self.remember_positions();
+        let mut entry: HistoryEntry = vec![];
+        let descendants = self.descendants(item.id);
+        self.step_remove(&mut entry, from);
+        for id in descendants {
+            let slot = self.slot_of(id);
+            self.step_remove(&mut entry, slot);
+        }
+        self.commit(entry, positions);
+    }
+
+    fn step_remove(&mut self, entry: &mut HistoryEntry, from: Slot) {
+        if let Some(item) = self.item_at(from).cloned() {
+            let mut removed = item.clone();
+            removed.stop_work(chrono::Utc::now());
+            let to = Slot::Removed(self.removed_items.len());
+            self.step(entry, Change { from, to, before: Some(item), after: Some(removed) });
+        }
+    }
+
+    // Completes the selected open item and every open subtask under it, as
+    // one step. The subtasks are put right after it in the DONE section.
+    pub fn complete_selected_item_with_children(&mut self) {
+        let from = self.selected_slot();
+        let item = match (from, self.item_at(from)) {
+            (Slot::Todo(_), Some(item)) => item.clone(),
+            _ => return,
+        };
+        let positions = self.remember_positions();
+        let now = chrono::Utc::now();
+        let mut entry: HistoryEntry = vec![];
+        let mut to = 0;
+        for id in std::iter::once(item.id).chain(self.descendants(item.id)) {
+            let from = if id == item.id { from } else { self.slot_of(id) };
+            if let Slot::Todo(_) = from {
+                self.step_complete(&mut entry, from, Slot::Done(to), now);
+                to += 1;
+            }
+        }
+        self.commit(entry, positions);
+    }
+
+    pub fn toggle_selected_item_completeness(&mut self) {
+        let from = self.selected_slot();
+        let item = match self.item_at(from) {
+            Some(item) => item.clone(),
+            None => return,
+        };
+        let positions = self.remember_positions();
+        let mut entry: HistoryEntry = vec![];
+        if let Slot::Todo(_) = from {
+            self.step_complete(&mut entry, from, Slot::Done(0), chrono::Utc::now());
+        } else {
+            let mut reopened = item.clone();
+            reopened.completed_at = None;
+            self.step(&mut entry, Change { from, to: Slot::Todo(self.todo_items.len()), before: Some(item), after: Some(reopened) });
+        }
+        self.commit(entry, positions);
+    }
+
+    // Completes the open item in `from`. Completing a recurring item adds its
+    // next occurrence in the same step.
+    fn step_complete(&mut self, entry: &mut HistoryEntry, from: Slot, to: Slot, now: chrono::DateTime<chrono::Utc>) {
+        let item = match self.item_at(from) {
+            Some(item) => item.clone(),
+            None => return,
+        };
+        let mut completed = item.clone();
+        completed.stop_work(now);
+        completed.completed_at = Some(now);
+        self.step(entry, Change { from, to, before: Some(item.clone()), after: Some(completed) });
+        if let Some(next) = item.next_occurrence(now) {
+            let next = self.identified(next);
+            self.step(entry, Change { from: Slot::Absent, to: Slot::Todo(self.todo_items.len()), before: None, after: Some(next) });
+        }
+    }
+
+    // Completes every marked open item, as one step, and clears the marks.
+    pub fn complete_marked_items(&mut self) {
+        let ids: Vec<u64> = self.todo_items.iter().filter(|item| self.is_marked(item)).map(|item| item.id).collect();
+        let positions = self.remember_positions();
+        let mut entry: HistoryEntry = vec![];
+        let now = chrono::Utc::now();
+        for (to, id) in ids.into_iter().enumerate() {
+            let from = self.slot_of(id);
+            self.step_complete(&mut entry, from, Slot::Done(to), now);
+        }
+        self.marked.clear();
+        self.commit(entry, positions);
+    }
+
+    // Reopens every marked finished item, as one step, and clears the marks.
+    pub fn reopen_marked_items(&mut self) {
+        let ids: Vec<u64> = self.done_items.iter().filter(|item| self.is_marked(item)).map(|item| item.id).collect();
+        let positions = self.remember_positions();
+        let mut entry: HistoryEntry = vec![];
+        for id in ids {
+            let from = self.slot_of(id);
+            if let Some(item) = self.item_at(from).cloned() {
+                let mut reopened = item.clone();
+                reopened.completed_at = None;
+                self.step(&mut entry, Change { from, to: Slot::Todo(self.todo_items.len()), before: Some(item), after: Some(reopened) });
+            }
+        }
+        self.marked.clear();
+        self.commit(entry, positions);
+    }
+
+    // Removes every marked item with its subtasks, as one step, and clears
+    // the marks.
+    pub fn remove_marked_items(&mut self) {
+        let mut ids: Vec<u64> = vec![];
+        for item in self.marked_items() {
+            for id in std::iter::once(item.id).chain(self.descendants(item.id)) {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+        let positions = self.remember_positions();
+        let mut entry: HistoryEntry = vec![];
+        for id in ids {
+            let from = self.slot_of(id);
+            self.step_remove(&mut entry, from);
+        }
+        self.marked.clear();
+        self.commit(entry, positions);
+    }
+
+    // Adds the tags to every marked item, as one step. The marks stay.
+    pub fn tag_marked_items(&mut self, tags: &[String]) {
+        let ids: Vec<u64> = self.marked_items().iter().map(|item| item.id).collect();
+        let positions = self.remember_positions();
+        let mut entry: HistoryEntry = vec![];
+        for id in ids {
+            let slot = self.slot_of(id);
+            if let Some(item) = self.item_at(slot).cloned() {
+                let mut tagged = item.clone();
+                for tag in tags {
+                    if !tagged.tags.contains(tag) {
+                        tagged.tags.push(tag.clone());
+                    }
+                }
+                if tagged != item {
+                    self.step(&mut entry, Change { from: slot, to: slot, before: Some(item), after: Some(tagged) });
+                }
+            }
+        }
+        self.commit(entry, positions);
+    }
+
+    // Starts or stops a work session on the selected open item. Only one item
+    // is worked on at a time, so starting stops any other, in the same step.
+    pub fn toggle_selected_item_working(&mut self) {
+        let slot = self.selected_slot();
+        let item = match (slot, self.item_at(slot)) {
+            (Slot::Todo(_), Some(item)) => item.clone(),
+            _ => return,
+        };
+        let now = chrono::Utc::now();
+        let positions = self.remember_positions();
+        let mut entry: HistoryEntry = vec![];
+        let mut toggled = item.clone();
+        if item.working {
+            toggled.stop_work(now);
+        } else {
+            toggled.start_work(now);
+        }
+        self.step(&mut entry, Change { from: slot, to: slot, before: Some(item), after: Some(toggled) });
+        if let Slot::Todo(selected) = slot {
+            for index in 0..self.todo_items.len() {
+                if index != selected && self.todo_items[index].working && self.todo_items[selected].working {
+                    let other = self.todo_items[index].clone();
+                    let mut stopped = other.clone();
+                    stopped.stop_work(now);
+                    self.step(&mut entry, Change { from: Slot::Todo(index), to: Slot::Todo(index), before: Some(other), after: Some(stopped) });
+                }
+            }
+        }
+        self.commit(entry, positions);
+    }
+
+    pub fn rename_item(&mut self, section: Section, index: usize, title: String) {
+        self.edit_item(section, index, |item| item.title = title);
+    }
+
+    pub fn set_item_tags(&mut self, section: Section, index: usize, tags: Vec<String>) {
+        self.edit_item(section, index, |item| item.tags = tags);
+    }
+
+    pub fn set_item_due(&mut self, section: Section, index: usize, due_at: Option<chrono::DateTime<chrono::Utc>>) {
+        self.edit_item(section, index, |item| item.due_at = due_at);
+    }
+
+    pub fn cycle_selected_item_priority(&mut self) {
+        let slot = self.selected_slot();
+        if let Some(item) = self.item_at(slot) {
+            let mut changed = item.clone();
+            changed.priority = item.next_priority();
+            self.update_item(slot, changed);
+        }
+    }
+
+    // Applies `edit` to a copy of the item and records it if anything changed.
+    pub fn edit_item(&mut self, section: Section, index: usize, edit: impl FnOnce(&mut TodoItem)) {
+        let slot = section_slot(section, index);
+        if let Some(item) = self.item_at(slot) {
+            let mut changed = item.clone();
+            edit(&mut changed);
+            if changed != *item {
+                self.update_item(slot, changed);
+            }
+        }
+    }
+
+    // Replaces the item in `slot` in place, as an undoable step.
+    pub fn update_item(&mut self, slot: Slot, item: TodoItem) {
+        let before = self.item_at(slot).cloned();
+        if before.is_some() {
+            self.execute(vec![Change { from: slot, to: slot, before, after: Some(item) }]);
+        }
+    }
+
+    // Takes back the last action and highlights the item it touched. Items
+    // removed in an earlier session can still be restored once this
+    // session's history runs out.
+    pub fn undo(&mut self) {
+        let entry = match self.undo_stack.pop() {
+            Some(entry) => entry,
+            None => return,
+        };
+        for change in entry.iter().rev() {
+            self.take_from(change.to);
+            if let Some(item) = &change.before {
+                self.put_into(change.from, item.clone());
+            }
+        }
+        self.select(entry[0].from);
+        self.redo_stack.push(entry);
+    }
+
+    pub fn redo(&mut self) {
+        let entry = match self.redo_stack.pop() {
+            Some(entry) => entry,
+            None => return,
+        };
+        self.apply(&entry);
+        self.select(entry[0].to);
+        self.undo_stack.push(entry);
+    }
+
+    fn execute(&mut self, entry: HistoryEntry) {
+        let positions = self.remember_positions();
+        self.apply(&entry);
+        self.commit(entry, positions);
+    }
+
+    // Applies one change of an action that's still being built. Each change's
+    // slots are where things are after the ones before it.
+    fn step(&mut self, entry: &mut HistoryEntry, change: Change) {
+        self.apply(std::slice::from_ref(&change));
+        entry.push(change);
+    }
+
+    fn remember_positions(&self) -> [usize; 2] {
+        return [Section::TODO, Section::DONE].map(|section| {
+            let highlighted = self.highlighted(section);
+            self.visible(section).iter().position(|i| *i == highlighted).unwrap_or(0)
+        });
+    }
+
+    // The highlight stays at the same position on screen, so completing or
+    // removing an item highlights the one shown after it.
+    fn commit(&mut self, entry: HistoryEntry, positions: [usize; 2]) {
+        if entry.len() == 0 {
+            return;
+        }
+        for (section, position) in [Section::TODO, Section::DONE].into_iter().zip(positions) {
+            let visible = self.visible(section);
+            if let Some(index) = visible.get(position.min(visible.len().saturating_sub(1))) {
+                self.set_highlighted(section, *index);
+            }
+        }
+        self.clamp_highlights();
+        self.undo_stack.push(entry);
+        self.redo_stack.clear();
+    }
+
+    fn apply(&mut self, entry: &[Change]) {
+        for change in entry {
+            self.take_from(change.from);
+            if let Some(item) = &change.after {
+                self.put_into(change.to, item.clone());
+            }
+        }
+    }
+
+    fn take_from(&mut self, slot: Slot) {
+        match slot {
+            Slot::Todo(index) => { self.todo_items.remove(index); },
+            Slot::Done(index) => { self.done_items.remove(index); },
+            Slot::Removed(index) => { self.removed_items.remove(index); },
+            Slot::Absent => {},
+        }
+    }
+
+    fn put_into(&mut self, slot: Slot, item: TodoItem) {
+        match slot {
+            Slot::Todo(index) => { self.todo_items.insert(index.min(self.todo_items.len()), item) },
+            Slot::Done(index) => { self.done_items.insert(index.min(self.done_items.len()), item) },
+            Slot::Removed(index) => { self.removed_items.insert(index.min(self.removed_items.len()), item) },
+            Slot::Absent => {},
+        }
+    }
+
+    // Moves the selection onto `slot` when it's in one of the visible sections.
+    fn select(&mut self, slot: Slot) {
+        match slot {
+            Slot::Todo(index) => {
+                self.active_section = Section::TODO;
+                self.highlighted_todo_item = index;
+            },
+            Slot::Done(index) => {
+                self.active_section = Section::DONE;
+                self.highlighted_done_item = index;
+            },
+            _ => {},
+        }
+        self.snap_highlights();
+    }
+
+    fn clamp_highlights(&mut self) {
+        self.highlighted_todo_item = self.highlighted_todo_item.min(self.todo_items.len().saturating_sub(1));
+        self.highlighted_done_item = self.highlighted_done_item.min(self.done_items.len().saturating_sub(1));
+    }
+
+    // Moves highlights off items the filter hides, onto the first shown item.
+    fn snap_highlights(&mut self) {
+        self.clamp_highlights();
+        for section in [Section::TODO, Section::DONE] {
+            let visible = self.visible(section);
+            if !visible.contains(&self.highlighted(section)) {
+                if let Some(index) = visible.first() {
+                    self.set_highlighted(section, *index);
+                }
+            }
+        }
+    }
+}
+
+fn section_slot(section: Section, index: usize) -> Slot {
+    return if section == Section::TODO { Slot::Todo(index) } else { Slot::Done(index) };
+}
+
+#[cfg(test)]
+mod undo_tests {
+    use super::*;
+    use crate::test_support::titles;
+
+    fn item(title: &str) -> TodoItem {
+        return TodoItem::new(title.to_string(), chrono::DateTime::parse_from_rfc3339("2024-04-01T13:39:57Z").unwrap().to_utc());
+    }
+
+    #[test]
+    fn rename_keeps_other_fields_and_can_be_undone() {
+        let mut items = TodoItems::new(vec![item("Feed dgos"), item("Write code")]);
+        items.rename_item(Section::TODO, 0, "Feed dogs".to_string());
+        assert_eq!(items.todo_items[0].title, "Feed dogs");
+        assert_eq!(items.todo_items[0].added_at, item("").added_at);
+
+        items.undo();
+        assert_eq!(items.todo_items[0].title, "Feed dgos");
+    }
+
+    #[test]
+    fn undo_takes_back_removes_and_renames_in_order() {
+        let mut items = TodoItems::new(vec![item("a"), item("b")]);
+        items.remove_selected_item();
+        items.rename_item(Section::TODO, 0, "c".to_string());
+
+        items.undo();
+        assert_eq!(titles(&items.todo_items), vec!["b"]);
+        items.undo();
+        assert_eq!(titles(&items.todo_items), vec!["a", "b"]);
+        assert_eq!(items.removed_items.len(), 0);
+    }
+
+    #[test]
+    fn undo_with_no_history_leaves_earlier_removes_alone() {
+        let mut items = TodoItems::new(vec![item("a")]);
+        items.removed_items.push(item("removed last session"));
+        items.undo();
+        assert_eq!(titles(&items.todo_items), vec!["a"]);
+        assert_eq!(items.removed_items.len(), 1);
+    }
+
+    #[test]
+    fn undoing_a_completion_puts_the_item_back_where_it_was_and_selects_it() {
+        let mut items = TodoItems::new(vec![item("a"), item("b"), item("c")]);
+        items.next_item();
+        items.toggle_selected_item_completeness();
+        items.toggle_section();
+        assert_eq!(titles(&items.done_items), vec!["b"]);
+
+        items.undo();
+        assert_eq!(titles(&items.todo_items), vec!["a", "b", "c"]);
+        assert_eq!(items.done_items.len(), 0);
+        assert_eq!(items.active_section, Section::TODO);
+        assert_eq!(items.selected_item().unwrap().title, "b");
+        assert_eq!(items.selected_item().unwrap().completed_at, None);
+    }
+
+    #[test]
+    fn redo_replays_undone_actions_until_something_new_happens() {
+        let mut items = TodoItems::new(vec![item("a"), item("b")]);
+        items.add_item("c".to_string());
+        items.toggle_selected_item_working();
+        items.undo();
+        items.undo();
+        assert_eq!(titles(&items.todo_items), vec!["a", "b"]);
+
+        items.redo();
+        items.redo();
+        assert_eq!(titles(&items.todo_items), vec!["a", "b", "c"]);
+        assert!(items.todo_items[2].working);
+        assert_eq!(items.selected_item().unwrap().title, "c");
+
+        items.undo();
+        items.remove_selected_item();
+        items.redo();
+        assert_eq!(titles(&items.todo_items), vec!["a", "b"]);
+        assert!(!items.removed_items[0].working);
+    }
+
+    #[test]
+    fn reopening_can_be_undone_into_the_done_section() {
+        let mut done = item("a");
+        done.completed_at = Some(done.added_at);
+        let mut items = TodoItems::new(vec![item("x"), done]);
+        items.toggle_section();
+        items.toggle_selected_item_completeness();
+        assert_eq!(titles(&items.todo_items), vec!["x", "a"]);
+
+        items.undo();
+        assert_eq!(titles(&items.done_items), vec!["a"]);
+        assert_eq!(items.active_section, Section::DONE);
+        items.redo();
+        assert_eq!(items.active_section, Section::TODO);
+        assert_eq!(items.selected_item().unwrap().title, "a");
+    }
+
+    #[test]
+    fn priority_cycles_through_a_to_e_and_back_to_none() {
+        let mut items = TodoItems::new(vec![item("a")]);
+        let mut seen = vec![];
+        for _ in 0..6 {
+            items.cycle_selected_item_priority();
+            seen.push(items.todo_items[0].priority);
+        }
+        assert_eq!(seen, vec![Some('A'), Some('B'), Some('C'), Some('D'), Some('E'), None]);
+        items.undo();
+        assert_eq!(items.todo_items[0].priority, Some('E'));
+    }
+}
+
+#[cfg(test)]
+mod view_tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn item(title: &str, minutes: i64) -> TodoItem {
+        return TodoItem::new(title.to_string(), chrono::Utc.with_ymd_and_hms(2024, 4, 1, 12, 0, 0).unwrap() + chrono::TimeDelta::minutes(minutes));
+    }
+
+    fn visible_titles(items: &TodoItems, section: Section) -> Vec<String> {
+        return items.visible(section).iter().map(|i| items.section_items(section)[*i].title.clone()).collect();
+    }
+
+    #[test]
+    fn sort_modes_order_the_view_but_not_the_items() {
+        let mut b = item("b", 0);
+        b.priority = Some('C');
+        let mut c = item("C", -10);
+        c.priority = Some('A');
+        c.due_at = Some(c.added_at);
+        let mut items = TodoItems::new(vec![b, item("a", 5), c]);
+
+        items.todo_sort = SortMode::Age;
+        assert_eq!(visible_titles(&items, Section::TODO), vec!["C", "b", "a"]);
+        items.todo_sort = SortMode::Title;
+        assert_eq!(visible_titles(&items, Section::TODO), vec!["a", "b", "C"]);
+        items.todo_sort = SortMode::Priority;
+        assert_eq!(visible_titles(&items, Section::TODO), vec!["C", "b", "a"]);
+        items.todo_sort = SortMode::Due;
+        assert_eq!(visible_titles(&items, Section::TODO), vec!["C", "b", "a"]);
+        assert_eq!(items.todo_items[0].title, "b");
+    }
+
+    #[test]
+    fn navigation_follows_the_sorted_filtered_view() {
+        let mut home = item("c", 0);
+        home.tags = vec!["home".to_string()];
+        let mut also_home = item("a", 0);
+        also_home.tags = vec!["home".to_string()];
+        let mut items = TodoItems::new(vec![home, item("b", 0), also_home]);
+        items.todo_sort = SortMode::Title;
+        items.set_filter(Filter { tag: Some("home".to_string()), ..Filter::default() });
+
+        assert_eq!(items.selected_item().unwrap().title, "c");
+        items.prev_item();
+        assert_eq!(items.selected_item().unwrap().title, "a");
+        items.next_item();
+        items.next_item();
+        assert_eq!(items.selected_item().unwrap().title, "c");
+
+        items.set_filter(Filter { working: true, ..Filter::default() });
+        assert_eq!(items.selected_slot(), Slot::Absent);
+    }
+}
+
+#[cfg(test)]
+mod tree_tests {
+    use super::*;
+
+    fn titles(items: &TodoItems, section: Section) -> Vec<(String, usize)> {
+        return items.visible_tree(section).iter().map(|(i, depth)| (items.section_items(section)[*i].title.clone(), *depth)).collect();
+    }
+
+    fn select(items: &mut TodoItems, title: &str) {
+        items.active_section = Section::TODO;
+        items.highlighted_todo_item = items.todo_items.iter().position(|item| item.title == title).unwrap();
+    }
+
+    // parent
+    //   one
+    //     deep
+    //   two
+    // other
+    fn tree() -> TodoItems {
+        let mut items = TodoItems::empty();
+        items.add_item("parent".to_string());
+        items.add_item("other".to_string());
+        select(&mut items, "parent");
+        items.add_child_to_selected_item("one".to_string());
+        items.add_child_to_selected_item("deep".to_string());
+        select(&mut items, "parent");
+        items.add_child_to_selected_item("two".to_string());
+        select(&mut items, "deep");
+        let deep = items.todo_items.remove(items.highlighted_todo_item);
+        let one = items.todo_items.iter().find(|item| item.title == "one").unwrap().id;
+        items.todo_items.push(TodoItem { parent: Some(one), ..deep });
+        return items;
+    }
+
+    #[test]
+    fn children_are_shown_indented_under_their_parent() {
+        let items = tree();
+        assert_eq!(titles(&items, Section::TODO), vec![
+            ("parent".to_string(), 0),
+            ("one".to_string(), 1),
+            ("deep".to_string(), 2),
+            ("two".to_string(), 1),
+            ("other".to_string(), 0),
+        ]);
+    }
+
+    #[test]
+    fn collapsing_hides_children_from_navigation() {
+        let mut items = tree();
+        select(&mut items, "parent");
+        items.toggle_selected_collapsed();
+        assert_eq!(titles(&items, Section::TODO).len(), 2);
+        assert_eq!(items.full_tree(Section::TODO).len(), 5);
+        items.next_item();
+        assert_eq!(items.selected_item().unwrap().title, "other");
+    }
+
+    #[test]
+    fn progress_counts_direct_children_in_both_sections() {
+        let mut items = tree();
+        select(&mut items, "two");
+        items.toggle_selected_item_completeness();
+        let parent = items.todo_items.iter().find(|item| item.title == "parent").unwrap();
+        assert_eq!(items.progress(parent), Some((1, 2)));
+        let other = items.todo_items.iter().find(|item| item.title == "other").unwrap();
+        assert_eq!(items.progress(other), None);
+    }
+
+    #[test]
+    fn completing_with_children_is_one_undoable_step() {
+        let mut items = tree();
+        select(&mut items, "parent");
+        items.complete_selected_item_with_children();
+        assert_eq!(titles(&items, Section::TODO), vec![("other".to_string(), 0)]);
+        assert_eq!(titles(&items, Section::DONE).len(), 4);
+        assert_eq!(items.done_items[0].title, "parent");
+
+        items.undo();
+        assert_eq!(titles(&items, Section::TODO), titles(&tree(), Section::TODO));
+        assert_eq!(items.done_items.len(), 0);
+        assert_eq!(items.selected_item().unwrap().title, "parent");
+    }
+
+    #[test]
+    fn removing_a_parent_removes_its_subtree() {
+        let mut items = tree();
+        select(&mut items, "one");
+        items.remove_selected_item();
+        assert_eq!(titles(&items, Section::TODO).len(), 3);
+        assert_eq!(items.removed_items.len(), 2);
+
+        items.undo();
+        assert_eq!(titles(&items, Section::TODO), titles(&tree(), Section::TODO));
+        items.redo();
+        assert_eq!(items.removed_items.len(), 2);
+    }
+
+    #[test]
+    fn items_without_ids_get_one_when_loaded() {
+        let mut items = TodoItems::new(vec![TodoItem::new("old".to_string(), chrono::Utc::now())]);
+        let id = items.todo_items[0].id;
+        assert_ne!(id, 0);
+        assert!(!items.assign_missing_ids());
+
+        items.add_child_to_selected_item("new".to_string());
+        assert_eq!(items.todo_items[1].parent, Some(id));
+        items.undo();
+        assert_eq!(items.todo_items.len(), 1);
+        assert_eq!(items.todo_items[0].id, id);
+    }
+}
+
+#[cfg(test)]
+mod move_tests {
+    use super::*;
+    use crate::test_support::titles;
+
+    #[test]
+    fn moving_keeps_the_item_selected_and_can_be_undone() {
+        let mut items = TodoItems::empty();
+        for title in ["a", "b", "c"] {
+            items.add_item(title.to_string());
+        }
+        items.move_selected_item_up().unwrap();
+        items.move_selected_item_up().unwrap();
+        assert_eq!(titles(&items.todo_items), vec!["c", "a", "b"]);
+        assert_eq!(items.selected_item().unwrap().title, "c");
+
+        items.move_selected_item_up().unwrap();
+        assert_eq!(titles(&items.todo_items), vec!["c", "a", "b"]);
+
+        items.move_selected_item_down().unwrap();
+        assert_eq!(titles(&items.todo_items), vec!["a", "c", "b"]);
+        items.undo();
+        assert_eq!(titles(&items.todo_items), vec!["c", "a", "b"]);
+        assert_eq!(items.selected_item().unwrap().title, "c");
+        items.redo();
+        assert_eq!(titles(&items.todo_items), vec!["a", "c", "b"]);
+    }
+
+    #[test]
+    fn subtasks_move_among_their_siblings_only() {
+        let mut items = TodoItems::empty();
+        items.add_item("parent".to_string());
+        items.add_child_to_selected_item("one".to_string());
+        items.highlighted_todo_item = 0;
+        items.add_child_to_selected_item("two".to_string());
+        items.add_item("other".to_string());
+        items.highlighted_todo_item = 0;
+
+        items.move_selected_item_down().unwrap();
+        let order: Vec<&str> = items.visible_tree(Section::TODO).iter().map(|(i, _)| items.todo_items[*i].title.as_str()).collect();
+        assert_eq!(order, vec!["other", "parent", "one", "two"]);
+
+        items.highlighted_todo_item = items.todo_items.iter().position(|item| item.title == "two").unwrap();
+        items.move_selected_item_down().unwrap();
+        assert_eq!(items.selected_item().unwrap().title, "two");
+        items.move_selected_item_up().unwrap();
+        let order: Vec<&str> = items.visible_tree(Section::TODO).iter().map(|(i, _)| items.todo_items[*i].title.as_str()).collect();
+        assert_eq!(order, vec!["other", "parent", "two", "one"]);
+    }
+
+    #[test]
+    fn sorted_sections_are_not_reordered() {
+        let mut items = TodoItems::empty();
+        items.add_item("b".to_string());
+        items.add_item("a".to_string());
+        items.cycle_sort_mode(Section::TODO);
+        assert_eq!(items.move_selected_item_up(), Err("sorted by age, change sort to manual to move items".to_string()));
+        assert_eq!(titles(&items.todo_items), vec!["b", "a"]);
+    }
+}
+
+#[cfg(test)]
+mod bulk_tests {
+    use super::*;
+    use crate::test_support::titles;
+
+    fn marked(titles: &[&str]) -> TodoItems {
+        let mut items = TodoItems::empty();
+        for title in ["a", "b", "c", "d"] {
+            items.add_item(title.to_string());
+        }
+        for index in 0..items.todo_items.len() {
+            if titles.contains(&items.todo_items[index].title.as_str()) {
+                items.highlighted_todo_item = index;
+                items.toggle_selected_marked();
+            }
+        }
+        return items;
+    }
+
+    #[test]
+    fn marking_twice_unmarks() {
+        let mut items = marked(&["b"]);
+        assert_eq!(titles(&items.marked_items().into_iter().cloned().collect::<Vec<_>>()), vec!["b"]);
+        items.highlighted_todo_item = 1;
+        items.toggle_selected_marked();
+        assert_eq!(items.marked_items().len(), 0);
+    }
+
+    #[test]
+    fn marking_uses_the_id_given_on_load() {
+        let mut items = TodoItems::new(vec![TodoItem::new("old".to_string(), chrono::Utc::now())]);
+        let id = items.todo_items[0].id;
+        items.toggle_selected_marked();
+        assert_eq!(items.todo_items[0].id, id);
+        assert_eq!(items.marked, HashSet::from([id]));
+    }
+
+    #[test]
+    fn bulk_complete_and_reopen_are_single_steps() {
+        let mut items = marked(&["a", "c"]);
+        items.complete_marked_items();
+        assert_eq!(titles(&items.todo_items), vec!["b", "d"]);
+        assert_eq!(titles(&items.done_items), vec!["a", "c"]);
+        assert_eq!(items.marked.len(), 0);
+
+        items.undo();
+        assert_eq!(titles(&items.todo_items), vec!["a", "b", "c", "d"]);
+        assert_eq!(items.done_items.len(), 0);
+        items.redo();
+
+        items.active_section = Section::DONE;
+        for index in 0..2 {
+            items.highlighted_done_item = index;
+            items.toggle_selected_marked();
+        }
+        items.reopen_marked_items();
+        assert_eq!(titles(&items.todo_items), vec!["b", "d", "a", "c"]);
+        items.undo();
+        assert_eq!(titles(&items.done_items), vec!["a", "c"]);
+    }
+
+    #[test]
+    fn bulk_delete_takes_subtasks_and_undoes_in_one_step() {
+        let mut items = marked(&["b", "d"]);
+        items.highlighted_todo_item = 1;
+        items.add_child_to_selected_item("b1".to_string());
+        items.remove_marked_items();
+        assert_eq!(titles(&items.todo_items), vec!["a", "c"]);
+        assert_eq!(items.removed_items.len(), 3);
+
+        items.undo();
+        assert_eq!(titles(&items.todo_items), vec!["a", "b", "c", "d", "b1"]);
+        assert_eq!(items.removed_items.len(), 0);
+    }
+
+    #[test]
+    fn bulk_tagging_keeps_existing_tags_and_marks() {
+        let mut items = marked(&["a", "b"]);
+        items.todo_items[0].tags = vec!["home".to_string()];
+        items.tag_marked_items(&["home".to_string(), "week".to_string()]);
+        assert_eq!(items.todo_items[0].tags, vec!["home", "week"]);
+        assert_eq!(items.todo_items[1].tags, vec!["home", "week"]);
+        assert_eq!(items.todo_items[2].tags.len(), 0);
+        assert_eq!(items.marked.len(), 2);
+
+        items.undo();
+        assert_eq!(items.todo_items[0].tags, vec!["home"]);
+        assert_eq!(items.todo_items[1].tags.len(), 0);
+    }
+}
+
+#[cfg(test)]
+mod work_tests {
+    use super::*;
+
+    #[test]
+    fn sessions_add_up_and_an_open_one_counts_to_now() {
+        let start = chrono::DateTime::parse_from_rfc3339("2024-04-01T09:00:00Z").unwrap().to_utc();
+        let mut item = TodoItem::new("a".to_string(), start);
+        item.start_work(start);
+        item.stop_work(start + chrono::TimeDelta::minutes(30));
+        item.start_work(start + chrono::TimeDelta::hours(2));
+        assert!(item.working);
+        assert_eq!(item.time_worked(start + chrono::TimeDelta::hours(3)), chrono::TimeDelta::minutes(90));
+        assert_eq!(format_worked(chrono::TimeDelta::minutes(90)), "1h30m");
+    }
+
+    #[test]
+    fn only_one_item_is_worked_on_at_a_time() {
+        let mut items = TodoItems::empty();
+        items.add_item("a".to_string());
+        items.add_item("b".to_string());
+        items.highlighted_todo_item = 0;
+        items.toggle_selected_item_working();
+        items.next_item();
+        items.toggle_selected_item_working();
+        assert!(!items.todo_items[0].working);
+        assert!(items.todo_items[0].sessions[0].ended_at.is_some());
+        assert!(items.todo_items[1].working);
+
+        items.undo();
+        assert!(items.todo_items[0].working);
+        assert!(!items.todo_items[1].working);
+    }
+
+    #[test]
+    fn completing_closes_the_running_session() {
+        let mut items = TodoItems::empty();
+        items.add_item("a".to_string());
+        items.toggle_selected_item_working();
+        items.toggle_selected_item_completeness();
+        assert!(!items.done_items[0].working);
+        assert_eq!(items.done_items[0].sessions.len(), 1);
+        assert!(items.done_items[0].sessions[0].ended_at.is_some());
+    }
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_match_finds_characters_in_order() {
+        assert_eq!(fuzzy_match("fdg", "Feed dogs"), Some(vec![0, 3, 7]));
+        assert_eq!(fuzzy_match("FEED D", "feed dogs"), Some(vec![0, 1, 2, 3, 5]));
+        assert_eq!(fuzzy_match("", "anything"), Some(vec![]));
+        assert_eq!(fuzzy_match("gdf", "Feed dogs"), None);
+    }
+
+    #[test]
+    fn search_matches_titles_or_tags() {
+        let mut item = TodoItem::new("Feed dogs".to_string(), chrono::Utc::now());
+        item.tags = vec!["home".to_string()];
+        assert!(item_matches_search(&item, "fdo"));
+        assert!(item_matches_search(&item, "hme"));
+        assert!(!item_matches_search(&item, "work"));
+    }
+}
+
+#[cfg(test)]
+mod due_tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn parses_dates_offsets_and_keywords() {
+        let now = chrono::Local.with_ymd_and_hms(2024, 4, 1, 9, 30, 0).unwrap();
+        let local = |text: &str| parse_due(text, now).unwrap().unwrap().with_timezone(&chrono::Local).naive_local().to_string();
+
+        assert_eq!(local("2024-04-03 18:00"), "2024-04-03 18:00:00");
+        assert_eq!(local("2024-04-03"), "2024-04-03 23:59:00");
+        assert_eq!(local("tomorrow"), "2024-04-02 23:59:00");
+        assert_eq!(local("+2h"), "2024-04-01 11:30:00");
+        assert_eq!(parse_due("", now), Ok(None));
+        assert!(parse_due("next week", now).is_err());
+        assert!(parse_due("+3w", now).is_err());
+        assert!(parse_due("+3é", now).is_err());
+        assert!(parse_due("+", now).is_err());
+        assert_eq!(parse_due("+99999999999d", now), Err("offset out of range".to_string()));
+        assert_eq!(parse_due("+9223372036854775807h", now), Err("offset out of range".to_string()));
+        assert_eq!(parse_due("+2562047788015d", now), Err("offset out of range".to_string()));
+    }
+
+    #[test]
+    fn due_state_depends_on_time_left_and_completion() {
+        let now = chrono::Utc.with_ymd_and_hms(2024, 4, 1, 12, 0, 0).unwrap();
+        let mut item = TodoItem::new("a".to_string(), now);
+        assert_eq!(item.due_state(now), None);
+
+        item.due_at = Some(now - chrono::TimeDelta::minutes(1));
+        assert_eq!(item.due_state(now), Some(DueState::Overdue));
+        item.due_at = Some(now + chrono::TimeDelta::hours(3));
+        assert_eq!(item.due_state(now), Some(DueState::DueSoon));
+        item.due_at = Some(now + chrono::TimeDelta::days(3));
+        assert_eq!(item.due_state(now), Some(DueState::Later));
+
+        item.completed_at = Some(now);
+        item.due_at = Some(now - chrono::TimeDelta::days(1));
+        assert_eq!(item.due_state(now), None);
+    }
+
+    #[test]
+    fn tags_are_split_and_deduplicated() {
+        assert_eq!(parse_tags("#home, work  home"), vec!["home", "work"]);
+        assert_eq!(parse_tags("  "), Vec::<String>::new());
+    }
+}
+
+#[cfg(test)]
+mod recurrence_tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn local(text: &str) -> chrono::DateTime<chrono::Utc> {
+        let naive = chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap();
+        return chrono::Local.from_local_datetime(&naive).unwrap().to_utc();
+    }
+
+    #[test]
+    fn parses_and_describes_rules() {
+        for text in ["daily", "weekly", "weekly mon,thu", "monthly", "every 3d"] {
+            assert_eq!(Recurrence::parse(text).unwrap().unwrap().describe(), text);
+        }
+        assert_eq!(Recurrence::parse("weekly Thursday, monday").unwrap(), Some(Recurrence::Weekly(vec![chrono::Weekday::Mon, chrono::Weekday::Thu])));
+        assert_eq!(Recurrence::parse("every 10 days").unwrap(), Some(Recurrence::AfterCompletion(10)));
+        assert_eq!(Recurrence::parse(""), Ok(None));
+        assert!(Recurrence::parse("weekly someday").is_err());
+        assert!(Recurrence::parse("every 0d").is_err());
+        assert!(Recurrence::parse("hourly").is_err());
+    }
+
+    #[test]
+    fn next_due_follows_each_rule() {
+        // 2024-04-01 is a Monday.
+        let due = local("2024-04-01 18:00");
+        let on_time = local("2024-04-01 17:00");
+        let next = |rule: &str, completed_at| Recurrence::parse(rule).unwrap().unwrap().next_due(due, completed_at).unwrap();
+
+        assert_eq!(next("daily", on_time), local("2024-04-02 18:00"));
+        assert_eq!(next("daily", local("2024-04-03 20:00")), local("2024-04-04 18:00"));
+        assert_eq!(next("weekly", on_time), local("2024-04-08 18:00"));
+        assert_eq!(next("weekly mon,thu", on_time), local("2024-04-04 18:00"));
+        assert_eq!(next("weekly mon,thu", local("2024-04-05 09:00")), local("2024-04-08 18:00"));
+        assert_eq!(next("monthly", on_time), local("2024-05-01 18:00"));
+        assert_eq!(next("every 3d", local("2024-04-05 09:00")), local("2024-04-08 18:00"));
+    }
+
+    #[test]
+    fn dates_past_the_last_one_end_the_recurrence() {
+        assert!(Recurrence::parse("every 4000000000d").is_err());
+        assert!(Recurrence::parse("every 36501d").is_err());
+        let last = chrono::DateTime::<chrono::Utc>::MAX_UTC - chrono::TimeDelta::hours(20);
+        for rule in ["daily", "weekly", "weekly mon", "monthly", "every 36500d"] {
+            let recurrence = Recurrence::parse(rule).unwrap().unwrap();
+            assert_eq!(recurrence.next_due(last, last), None, "{}", rule);
+        }
+
+        let mut item = TodoItem::new("Feed dogs".to_string(), chrono::Utc::now());
+        item.due_at = Some(last);
+        item.recurrence = Some(Recurrence::Monthly);
+        assert_eq!(item.next_occurrence(last), None);
+    }
+
+    #[test]
+    fn completing_a_recurring_item_adds_the_next_one() {
+        let mut items = TodoItems::empty();
+        items.add_item("Feed dogs".to_string());
+        items.todo_items[0].tags = vec!["pets".to_string()];
+        items.todo_items[0].due_at = Some(chrono::Utc::now() + chrono::TimeDelta::hours(1));
+        items.todo_items[0].recurrence = Some(Recurrence::Daily);
+        let due_at = items.todo_items[0].due_at.unwrap();
+
+        items.toggle_selected_item_completeness();
+        assert_eq!(items.done_items[0].title, "Feed dogs");
+        assert_eq!(items.todo_items.len(), 1);
+        let next = &items.todo_items[0];
+        assert_eq!(next.title, "Feed dogs");
+        assert_eq!(next.tags, vec!["pets".to_string()]);
+        assert_eq!(next.recurrence, Some(Recurrence::Daily));
+        assert_eq!(next.completed_at, None);
+        assert_eq!(next.due_at, Recurrence::Daily.next_due(due_at, next.added_at));
+        assert_ne!(next.id, items.done_items[0].id);
+
+        items.undo();
+        assert_eq!(items.todo_items.len(), 1);
+        assert_eq!(items.todo_items[0].due_at, Some(due_at));
+        assert_eq!(items.done_items.len(), 0);
+    }
+
+    #[test]
+    fn completing_with_children_adds_their_next_ones() {
+        let mut items = TodoItems::empty();
+        items.add_item("Chores".to_string());
+        items.add_child_to_selected_item("Feed dogs".to_string());
+        items.todo_items[1].due_at = Some(chrono::Utc::now() + chrono::TimeDelta::hours(1));
+        items.todo_items[1].recurrence = Some(Recurrence::Daily);
+        items.highlighted_todo_item = 0;
+
+        items.complete_selected_item_with_children();
+        assert_eq!(items.done_items.len(), 2);
+        assert_eq!(items.todo_items.len(), 1);
+        assert_eq!(items.todo_items[0].title, "Feed dogs");
+        assert_eq!(items.todo_items[0].recurrence, Some(Recurrence::Daily));
+
+        items.undo();
+        assert_eq!(items.todo_items.len(), 2);
+        assert_eq!(items.done_items.len(), 0);
+    }
+
+    #[test]
+    fn reopening_a_recurring_item_adds_nothing() {
+        let mut done = TodoItem::new("Feed dogs".to_string(), chrono::Utc::now());
+        done.completed_at = Some(chrono::Utc::now());
+        done.recurrence = Some(Recurrence::Monthly);
+        let mut items = TodoItems::new(vec![done]);
+        items.toggle_section();
+        items.toggle_selected_item_completeness();
+        assert_eq!(items.todo_items.len(), 1);
+        assert_eq!(items.done_items.len(), 0);
+    }
+}
@@ -0,0 +1,272 @@
+use std::path::PathBuf;
+use std::{env, fmt, fs, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::todo::{TodoItem, TodoItems};
+
+const FILE_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    // The file exists but isn't something we wrote. We never save over it.
+    Corrupt(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageError::Io(error) => write!(f, "{}", error),
+            StorageError::Corrupt(message) => write!(f, "corrupt todo file: {}", message),
+        }
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(error: io::Error) -> StorageError {
+        return StorageError::Io(error);
+    }
+}
+
+// Where the todo list lives. `TodoItems` itself knows nothing about storage.
+pub trait TodoStore {
+    // A store with nothing in it yet loads as an empty list.
+    fn load(&mut self) -> Result<TodoItems, StorageError>;
+    fn save(&mut self, items: &TodoItems) -> Result<(), StorageError>;
+    // Adds a single item to the end of its section without rewriting the rest.
+    fn append(&mut self, item: &TodoItem) -> Result<(), StorageError>;
+    // Shown to the user when something goes wrong, e.g. the file path.
+    fn describe(&self) -> String;
+}
+
+#[derive(Serialize, Deserialize)]
+struct TodoFile {
+    version: u32,
+    todo_items: Vec<TodoItem>,
+    done_items: Vec<TodoItem>,
+    removed_items: Vec<TodoItem>,
+}
+
+// $XDG_DATA_HOME/termod/todos.json, falling back to ~/.local/share.
+pub fn default_path() -> Option<PathBuf> {
+    let data_home = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local").join("share"),
+    };
+    return Some(data_home.join("termod").join("todos.json"));
+}
+
+// The JSON file termod uses by default, see `default_path`.
+pub struct JsonFileStore {
+    pub path: PathBuf,
+}
+
+impl JsonFileStore {
+    pub fn new(path: PathBuf) -> JsonFileStore {
+        return JsonFileStore { path };
+    }
+}
+
+impl TodoStore for JsonFileStore {
+    fn load(&mut self) -> Result<TodoItems, StorageError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(TodoItems::empty()),
+            Err(error) => return Err(StorageError::Io(error)),
+        };
+
+        let file: TodoFile = serde_json::from_str(&contents).map_err(|error| StorageError::Corrupt(error.to_string()))?;
+        if file.version > FILE_VERSION {
+            return Err(StorageError::Corrupt(format!("unsupported version {}", file.version)));
+        }
+
+        let mut items = TodoItems::empty();
+        items.todo_items = file.todo_items;
+        items.done_items = file.done_items;
+        items.removed_items = file.removed_items;
+        return Ok(items);
+    }
+
+    // Writes to a temporary file next to the target and renames it into place,
+    // so a crash mid-write never leaves a half written list behind.
+    fn save(&mut self, items: &TodoItems) -> Result<(), StorageError> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let file = TodoFile {
+            version: FILE_VERSION,
+            todo_items: items.todo_items.clone(),
+            done_items: items.done_items.clone(),
+            removed_items: items.removed_items.clone(),
+        };
+        let contents = serde_json::to_string_pretty(&file).map_err(|error| StorageError::Corrupt(error.to_string()))?;
+
+        let mut tmp_path = self.path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        fs::write(&tmp_path, contents)?;
+        fs::rename(&tmp_path, &self.path)?;
+        return Ok(());
+    }
+
+    fn append(&mut self, item: &TodoItem) -> Result<(), StorageError> {
+        let mut items = self.load()?;
+        items.push_item(item.clone());
+        return self.save(&items);
+    }
+
+    fn describe(&self) -> String {
+        return self.path.display().to_string();
+    }
+}
+
+// Keeps everything in memory, for tests and when there's nowhere to save to.
+#[derive(Default)]
+pub struct MemoryStore {
+    pub todo_items: Vec<TodoItem>,
+    pub done_items: Vec<TodoItem>,
+    pub removed_items: Vec<TodoItem>,
+}
+
+impl MemoryStore {
+    pub fn new(items: Vec<TodoItem>) -> MemoryStore {
+        let items = TodoItems::new(items);
+        return MemoryStore {
+            todo_items: items.todo_items,
+            done_items: items.done_items,
+            removed_items: items.removed_items,
+        };
+    }
+}
+
+impl TodoStore for MemoryStore {
+    fn load(&mut self) -> Result<TodoItems, StorageError> {
+        let mut items = TodoItems::empty();
+        items.todo_items = self.todo_items.clone();
+        items.done_items = self.done_items.clone();
+        items.removed_items = self.removed_items.clone();
+        return Ok(items);
+    }
+
+    fn save(&mut self, items: &TodoItems) -> Result<(), StorageError> {
+        self.todo_items = items.todo_items.clone();
+        self.done_items = items.done_items.clone();
+        self.removed_items = items.removed_items.clone();
+        return Ok(());
+    }
+
+    fn append(&mut self, item: &TodoItem) -> Result<(), StorageError> {
+        if item.completed_at == None {
+            self.todo_items.push(item.clone());
+        } else {
+            self.done_items.push(item.clone());
+        }
+        return Ok(());
+    }
+
+    fn describe(&self) -> String {
+        return "memory".to_string();
+    }
+}
+
+// Picks the store termod uses: SQLite when built with the `sqlite` feature and
+// TERMOD_STORE=sqlite, otherwise the JSON file.
+pub fn open_default_store() -> Box<dyn TodoStore> {
+    let path = match default_path() {
+        Some(path) => path,
+        None => return Box::new(MemoryStore::default()),
+    };
+
+    #[cfg(feature = "sqlite")]
+    if env::var("TERMOD_STORE").as_deref() == Ok("sqlite") {
+        return Box::new(crate::sqlite_store::SqliteStore::new(path.with_extension("db")));
+    }
+
+    return Box::new(JsonFileStore::new(path));
+}
+
+#[cfg(test)]
+mod todo_store_tests {
+    use super::*;
+    use crate::test_support::titles;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("termod-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        return dir.join("todos.json");
+    }
+
+    fn item(title: &str, completed: bool) -> TodoItem {
+        let added_at = chrono::DateTime::parse_from_rfc3339("2024-04-01T13:39:57Z").unwrap().to_utc();
+        let mut item = TodoItem::new(title.to_string(), added_at);
+        item.completed_at = if completed { Some(added_at + chrono::TimeDelta::hours(1)) } else { None };
+        item.working = !completed;
+        return item;
+    }
+
+    #[test]
+    fn save_then_load_round_trips_every_section() {
+        let path = temp_path("round-trip");
+        let mut tagged = item("Buy groceries", false);
+        tagged.priority = Some('B');
+        tagged.tags = vec!["home".to_string()];
+        tagged.due_at = Some(tagged.added_at + chrono::TimeDelta::days(2));
+        tagged.id = 7;
+        tagged.parent = Some(3);
+        tagged.collapsed = true;
+        let mut items = TodoItems::new(vec![tagged, item("Write code", true)]);
+        items.removed_items.push(item("Feed dogs", false));
+
+        let mut store = JsonFileStore::new(path.clone());
+        store.save(&items).unwrap();
+        let loaded = store.load().unwrap();
+
+        assert_eq!(loaded.todo_items, items.todo_items);
+        assert_eq!(loaded.done_items, items.done_items);
+        assert_eq!(loaded.removed_items, items.removed_items);
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn a_missing_file_loads_as_an_empty_list() {
+        let loaded = JsonFileStore::new(temp_path("missing")).load().unwrap();
+        assert_eq!(loaded.todo_items.len(), 0);
+    }
+
+    #[test]
+    fn files_from_before_priorities_and_tags_still_load() {
+        let path = temp_path("old-items");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let old_item = r#"{"title": "Feed dogs", "completed_at": null, "added_at": "2024-04-01T13:39:57Z", "working": false}"#;
+        fs::write(&path, format!(r#"{{"version": 1, "todo_items": [{}], "done_items": [], "removed_items": []}}"#, old_item)).unwrap();
+
+        let loaded = JsonFileStore::new(path).load().unwrap();
+        assert_eq!(loaded.todo_items[0].priority, None);
+        assert_eq!(loaded.todo_items[0].tags.len(), 0);
+        assert_eq!(loaded.todo_items[0].due_at, None);
+        assert!(!loaded.todo_items[0].collapsed);
+    }
+
+    #[test]
+    fn a_corrupt_file_is_reported() {
+        let path = temp_path("corrupt");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{ not json").unwrap();
+
+        assert!(matches!(JsonFileStore::new(path).load(), Err(StorageError::Corrupt(_))));
+    }
+
+    #[test]
+    fn append_adds_to_the_matching_section() {
+        let path = temp_path("append");
+        let mut store = JsonFileStore::new(path);
+        store.append(&item("Buy groceries", false)).unwrap();
+        store.append(&item("Write code", true)).unwrap();
+
+        let loaded = store.load().unwrap();
+        assert_eq!(titles(&loaded.todo_items), vec!["Buy groceries"]);
+        assert_eq!(titles(&loaded.done_items), vec!["Write code"]);
+    }
+}
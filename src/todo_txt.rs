@@ -0,0 +1,241 @@
+// Reading and writing the todo.txt format, see
+// https://github.com/todotxt/todo.txt. Projects (+work) become tags, contexts
+// keep their '@' (@home) so they can be told apart on the way back out.
+// Items carry their UUID as uuid:..., so importing a file written here
+// updates the items it came from instead of adding them again.
+//
+// todo.txt has no escaping, so title words that would otherwise be read as
+// tags or key:value pairs are written with a leading '\'. That's termod's own
+// convention and lossy elsewhere: other todo.txt tools show the '\' as part
+// of the word, and their words that start with one lose it here.
+use crate::todo::{TodoItem, TodoItems};
+
+fn parse_date(token: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let date = chrono::NaiveDate::parse_from_str(token, "%Y-%m-%d").ok()?;
+    return date.and_time(chrono::NaiveTime::MIN).and_local_timezone(chrono::Local).earliest().map(|at| at.to_utc());
+}
+
+fn format_date(at: chrono::DateTime<chrono::Utc>) -> String {
+    return at.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string();
+}
+
+fn parse_priority(token: &str) -> Option<char> {
+    let mut chars = token.chars();
+    return match (chars.next(), chars.next(), chars.next(), chars.next()) {
+        (Some('('), Some(priority), Some(')'), None) if priority.is_ascii_uppercase() => Some(priority),
+        _ => None,
+    };
+}
+
+// "key:value" with no spaces, where the key is a word starting with a letter
+// and the value isn't empty. Times (10:30) and URLs aren't extensions.
+fn parse_extension(token: &str) -> Option<(String, String)> {
+    let (key, value) = token.split_once(':')?;
+    let is_key = key.starts_with(|c: char| c.is_alphabetic()) && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    if !is_key || value.len() == 0 || value.starts_with("//") {
+        return None;
+    }
+    return Some((key.to_string(), value.to_string()));
+}
+
+// +project or @context, whose name starts with a letter, so "+1" is a word.
+fn parse_tag(token: &str) -> Option<String> {
+    let name = token.strip_prefix(['+', '@'])?;
+    if !name.starts_with(|c: char| c.is_alphabetic()) {
+        return None;
+    }
+    return Some(if token.starts_with('@') { token.to_string() } else { name.to_string() });
+}
+
+// Tags can't hold spaces in todo.txt.
+fn format_tag(tag: &str) -> String {
+    let tag = tag.split_whitespace().collect::<Vec<_>>().join("_");
+    return if tag.starts_with('@') { tag } else { format!("+{}", tag) };
+}
+
+// Parses one line, `None` for blank lines. Items without a creation date are
+// treated as added at `now`.
+pub fn parse_line(line: &str, now: chrono::DateTime<chrono::Utc>) -> Option<TodoItem> {
+    let mut tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() == 0 {
+        return None;
+    }
+    tokens.reverse();
+
+    let mut item = TodoItem::new("".to_string(), now);
+    if tokens.last() == Some(&"x") {
+        tokens.pop();
+        item.completed_at = Some(now);
+        if let Some(completed_at) = tokens.last().and_then(|token| parse_date(token)) {
+            tokens.pop();
+            item.completed_at = Some(completed_at);
+        }
+    } else if let Some(priority) = tokens.last().and_then(|token| parse_priority(token)) {
+        tokens.pop();
+        item.priority = Some(priority);
+    }
+    if let Some(added_at) = tokens.last().and_then(|token| parse_date(token)) {
+        tokens.pop();
+        item.added_at = added_at;
+    }
+
+    // Tags can go anywhere, key:value pairs only after the last title word so
+    // "Meet at noon:ish today" keeps its words in order.
+    tokens.reverse();
+    let is_word = |token: &&str| token.starts_with('\\') || (parse_tag(token).is_none() && parse_extension(token).is_none());
+    let last_word = tokens.iter().rposition(is_word);
+    let mut title: Vec<&str> = vec![];
+    for (position, token) in tokens.into_iter().enumerate() {
+        if let Some(escaped) = token.strip_prefix('\\') {
+            title.push(escaped);
+        } else if let Some(tag) = parse_tag(token) {
+            item.tags.push(tag);
+        } else if let Some((key, value)) = parse_extension(token).filter(|_| last_word.is_none_or(|last| position > last)) {
+            match key.as_str() {
+                // Completed items keep their priority as pri:A.
+                "pri" if item.priority.is_none() && value.len() == 1 => { item.priority = value.chars().next() },
+                "due" if item.due_at.is_none() && parse_date(&value).is_some() => {
+                    item.due_at = parse_date(&value).map(|due| due + chrono::TimeDelta::hours(23) + chrono::TimeDelta::minutes(59));
+                },
+                "uuid" if item.uuid.is_none() => { item.uuid = Some(value) },
+                _ => { item.extensions.push((key, value)) },
+            }
+        } else {
+            title.push(token);
+        }
+    }
+    item.title = title.join(" ");
+    return Some(item);
+}
+
+pub fn parse(text: &str, now: chrono::DateTime<chrono::Utc>) -> Vec<TodoItem> {
+    return text.lines().filter_map(|line| parse_line(line, now)).collect();
+}
+
+pub fn format_item(item: &TodoItem) -> String {
+    let mut tokens: Vec<String> = vec![];
+    match (item.completed_at, item.priority) {
+        (Some(completed_at), _) => {
+            tokens.push("x".to_string());
+            tokens.push(format_date(completed_at));
+        },
+        (None, Some(priority)) => { tokens.push(format!("({})", priority)) },
+        (None, None) => {},
+    }
+    tokens.push(format_date(item.added_at));
+    let words: Vec<&str> = item.title.split_whitespace().collect();
+    for (position, word) in words.iter().enumerate() {
+        // Only the last word could be taken for a key:value pair, as one that's
+        // escaped counts as a word.
+        let is_last = position + 1 == words.len();
+        if word.starts_with('\\') || parse_tag(word).is_some() || (is_last && parse_extension(word).is_some()) {
+            tokens.push(format!("\\{}", word));
+        } else {
+            tokens.push(word.to_string());
+        }
+    }
+    for tag in &item.tags {
+        tokens.push(format_tag(tag));
+    }
+    if let (Some(_), Some(priority)) = (item.completed_at, item.priority) {
+        tokens.push(format!("pri:{}", priority));
+    }
+    if let Some(due_at) = item.due_at {
+        tokens.push(format!("due:{}", format_date(due_at)));
+    }
+    if let Some(uuid) = &item.uuid {
+        tokens.push(format!("uuid:{}", uuid));
+    }
+    for (key, value) in &item.extensions {
+        tokens.push(format!("{}:{}", key, value));
+    }
+    return tokens.join(" ");
+}
+
+// Open items first, then finished ones. Removed items aren't written.
+pub fn write(items: &TodoItems) -> String {
+    let mut text = String::new();
+    for item in items.todo_items.iter().chain(items.done_items.iter()) {
+        text.push_str(&format_item(item));
+        text.push('\n');
+    }
+    return text;
+}
+
+#[cfg(test)]
+mod todo_txt_tests {
+    use super::*;
+    use crate::test_support::now;
+
+    #[test]
+    fn reads_priorities_dates_tags_and_extensions() {
+        let item = parse_line("(A) 2024-04-01 Call mom +family @phone rec:1w due:2024-04-03", now()).unwrap();
+        assert_eq!(item.title, "Call mom");
+        assert_eq!(item.priority, Some('A'));
+        assert_eq!(format_date(item.added_at), "2024-04-01");
+        assert_eq!(item.tags, vec!["family", "@phone"]);
+        assert_eq!(item.extensions, vec![("rec".to_string(), "1w".to_string())]);
+        assert_eq!(format_date(item.due_at.unwrap()), "2024-04-03");
+
+        let done = parse_line("x 2024-04-02 2024-04-01 Pay rent pri:B", now()).unwrap();
+        assert_eq!(format_date(done.completed_at.unwrap()), "2024-04-02");
+        assert_eq!(done.priority, Some('B'));
+        assert_eq!(done.title, "Pay rent");
+    }
+
+    #[test]
+    fn lines_round_trip() {
+        for line in [
+            "(A) 2024-04-01 Call mom +family @phone due:2024-04-03 rec:1w",
+            "x 2024-04-02 2024-04-01 Pay rent +home pri:B id:42",
+            "2024-04-01 Water plants due:2024-04-03 uuid:0f8e5b2a-3c1d-4e7f-9a6b-2d4c8e1f0a3b",
+            "2024-04-01 Read https://example.com/a:b",
+            "2024-04-01 Meet at 10:30 re: ratio 3:2 note:one two +work",
+            "2024-04-01 Give +1 to \\@bob about \\+work and \\\\n \\key:value",
+        ] {
+            assert_eq!(format_item(&parse_line(line, now()).unwrap()), line);
+        }
+    }
+
+    #[test]
+    fn items_round_trip() {
+        let mut item = TodoItem::new("Give +1 to @bob at 10:30, ratio 3:2 \\o/ note:later".to_string(), now());
+        item.priority = Some('B');
+        item.tags = vec!["big project".to_string(), "@home".to_string()];
+        item.due_at = parse_date("2024-04-07").map(|due| due + chrono::TimeDelta::hours(23) + chrono::TimeDelta::minutes(59));
+        item.extensions = vec![("id".to_string(), "42".to_string()), ("rec".to_string(), "1w".to_string())];
+        item.added_at = parse_date("2024-04-01").unwrap();
+
+        let parsed = parse_line(&format_item(&item), now()).unwrap();
+        assert_eq!(parsed.title, item.title);
+        assert_eq!(parsed.tags, vec!["big_project", "@home"]);
+        assert_eq!(parsed.priority, item.priority);
+        assert_eq!(parsed.due_at, item.due_at);
+        assert_eq!(parsed.extensions, item.extensions);
+        assert_eq!(format_item(&parsed), format_item(&item).replace("big project", "big_project"));
+    }
+
+    #[test]
+    fn importing_an_export_updates_the_items_it_came_from() {
+        let mut items = TodoItems::new(vec![TodoItem::new("Water plants".to_string(), now())]);
+        let uuid = items.todo_items[0].uuid.clone();
+        assert!(uuid.is_some());
+
+        let mut exported = parse(&write(&items), now());
+        assert_eq!(exported[0].uuid, uuid);
+        assert!(exported[0].extensions.is_empty());
+        exported[0].title = "Water all plants".to_string();
+        items.merge(TodoItems::new(exported));
+        assert_eq!(items.todo_items.len(), 1);
+        assert_eq!(items.todo_items[0].title, "Water all plants");
+        assert_eq!(items.todo_items[0].uuid, uuid);
+    }
+
+    #[test]
+    fn lines_without_dates_are_added_now() {
+        let items = parse("\nBuy milk\n", now());
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].added_at, now());
+        assert_eq!(items[0].completed_at, None);
+    }
+}
@@ -1,229 +1,621 @@
+use std::collections::BTreeMap;
+
+use crate::app::Navigation;
 use crate::buffer::Buffer;
-use crate::{character, colours};
-use crate::widget::{Widget, WidgetTrait};
+use crate::{character, colours, export, report, text_view_widget};
+use crate::event_bus::AppEvent;
+use crate::text_input::TextInput;
+use crate::todo::{self, DueState, Filter, Recurrence, Section, TodoItem, TodoItems};
+use crate::todo_store::{StorageError, TodoStore};
+use crate::widget::{self, EventResult, Widget, WidgetTrait};
 use crossterm::{event, style};
 use chrono;
 
+fn help_lines() -> Vec<String> {
+    return vec![
+        "Up/Down     move selection".to_string(),
+        "Shift-Up/Dn move item".to_string(),
+        "Left/Right  switch section".to_string(),
+        "Enter       toggle complete".to_string(),
+        "w           toggle working".to_string(),
+        "d           delete".to_string(),
+        "u           undo".to_string(),
+        "Ctrl-R      redo".to_string(),
+        "o           open details".to_string(),
+        "R           report over dates".to_string(),
+        "a           add a todo".to_string(),
+        "A           add a subtask".to_string(),
+        "X           complete with subtasks".to_string(),
+        "c           collapse/expand".to_string(),
+        "e           edit title".to_string(),
+        "p           cycle priority".to_string(),
+        "t           edit tags".to_string(),
+        "D           edit due date".to_string(),
+        "r           edit repeat".to_string(),
+        "s           change sort".to_string(),
+        "f           filter by tag".to_string(),
+        "/           search".to_string(),
+        "!           only overdue".to_string(),
+        "W           only working".to_string(),
+        "v / Space   mark items".to_string(),
+        "  Shift-Up/Dn  mark a range".to_string(),
+        "  Enter       complete/reopen marked".to_string(),
+        "  d           delete marked".to_string(),
+        "  t           tag marked".to_string(),
+        "  m           export marked".to_string(),
+        "Esc         back / quit".to_string(),
+    ];
+}
 
-#[derive(PartialEq)]
-enum Section {
-    TODO, 
-    DONE,
+#[derive(Clone, Copy, PartialEq)]
+enum ItemField {
+    Title,
+    Tags,
+    Due,
+    Recurrence,
 }
 
-struct TodoItem {
-    title: String,
-    completed_at: Option<chrono::DateTime<chrono::Utc>>,
-    added_at: chrono::DateTime<chrono::Utc>,
-    working: bool,
+#[derive(Clone, Copy, PartialEq)]
+enum InputTarget {
+    NewItem,
+    NewChild,
+    EditItem { section: Section, index: usize, field: ItemField },
+    Filter,
+    Search,
+    MarkedTags,
+    MarkedExport,
+    ReportRange,
 }
 
-impl TodoItem {
-    pub fn calculate_age(&self) -> chrono::TimeDelta {
-        return chrono::Utc::now() - self.added_at;
+impl InputTarget {
+    // Prompts of the same kind share an input and its history. Also the
+    // input's placeholder.
+    fn prompt(&self) -> &'static str {
+        return match self {
+            InputTarget::NewItem | InputTarget::NewChild | InputTarget::EditItem { field: ItemField::Title, .. } => "Title",
+            InputTarget::EditItem { field: ItemField::Tags, .. } | InputTarget::MarkedTags => "Tags",
+            InputTarget::EditItem { field: ItemField::Due, .. } => "Due",
+            InputTarget::EditItem { field: ItemField::Recurrence, .. } => "Repeats",
+            InputTarget::Filter => "Tag",
+            InputTarget::Search => "Search",
+            InputTarget::MarkedExport => "Format and file",
+            InputTarget::ReportRange => "Date range",
+        };
     }
 }
 
-struct TodoItems {
-    active_section: Section,
-    todo_items: Vec<TodoItem>,
-    done_items: Vec<TodoItem>,
-    removed_items: Vec<TodoItem>,
-    highlighted_todo_item: usize,
-    highlighted_done_item: usize,
+// Sort modes and filters are shown on the first row.
+const HEADER_ROWS: usize = 1;
+
+pub struct TodoState {
+    main_buffer: Buffer,
+    update_main_buffer: bool,
+    items: TodoItems,
+    item_seperator_height: usize,
+    ages_last_calculated_at: std::time::Instant,
+    store: Box<dyn TodoStore>,
+    storage_error: Option<String>,
+    // Why the last save failed, when trying again might work.
+    save_error: Option<String>,
+    // One input per kind of prompt, so each keeps its own history.
+    inputs: BTreeMap<&'static str, TextInput>,
+    // Keys act on the marked items rather than the highlighted one.
+    visual: bool,
+    // What the title input is for, while it's open.
+    input_target: Option<InputTarget>,
+    // Why the last thing typed into the input was rejected.
+    input_error: Option<String>,
+    // What the last action did, when that isn't visible in the list.
+    notice: Option<String>,
+    // The section and highlights to go back to when a search is cancelled.
+    selection_before_search: (Section, usize, usize),
+    // What other widgets were last told about each item, by id.
+    reported: BTreeMap<u64, ReportedItem>,
 }
 
-impl TodoItems {
-    pub fn new(items: Vec<TodoItem>) -> TodoItems {
-        let mut todo_items: Vec<TodoItem> = vec![];
-        let mut done_items: Vec<TodoItem>= vec![];
-        for item in items {
-            if item.completed_at == None {
-                todo_items.push(item);
-            } else {
-                done_items.push(item);
+#[derive(Clone, PartialEq)]
+struct ReportedItem {
+    title: String,
+    done: bool,
+    working: bool,
+}
+
+impl WidgetTrait for Widget<TodoState> {
+    fn init(&mut self) {self.init()}
+    fn handle_event(&mut self, event_to_handle: &event::Event) -> EventResult {self.handle_event(event_to_handle)}
+    fn handle_app_event(&mut self, app_event: &AppEvent) {self.handle_app_event(app_event)}
+    fn take_published_events(&mut self) -> Vec<AppEvent> {self.take_published_events()}
+    fn take_navigation_requests(&mut self) -> Vec<Navigation> {self.take_navigation_requests()}
+    fn update(&mut self) {self.update()}
+    fn draw(&mut self) {self.draw()}
+    fn generate_buffer(&mut self) -> Buffer {self.generate_buffer()}
+    fn get_title(&self) -> &String {&self.title}
+    fn get_size(&self) -> (u16, u16) {(self.width, self.height)}
+    fn get_cursor_position(&self) -> Option<(u16, u16)> {self.cursor_position}
+}
+
+fn init(myself: &mut Widget<TodoState>) {
+    match myself.state.store.load() {
+        Ok(items) => {
+            myself.state.items = items;
+            if myself.state.items.assign_missing_ids() {
+                save_items(myself);
             }
-        }
+        },
+        Err(error) => {
+            myself.state.storage_error = Some(format!("Not saving, couldn't read {}: {}", myself.state.store.describe(), error));
+        },
+    }
+    // Nothing's been reported yet, so this tells others what's being worked on.
+    publish_changes(myself);
+}
 
-        return TodoItems {
-            active_section: Section::TODO,
-            todo_items,
-            done_items,
-            removed_items: vec![],
-            highlighted_todo_item: 0,
-            highlighted_done_item: 0,
+// Tells other widgets which items were completed, reopened, started or
+// stopped since the last call, whatever changed them, e.g. undo and redo.
+fn publish_changes(myself: &mut Widget<TodoState>) {
+    let items = &myself.state.items;
+    let current: BTreeMap<u64, ReportedItem> = items.todo_items.iter().chain(items.done_items.iter())
+        .map(|item| (item.id, ReportedItem { title: item.title.clone(), done: item.completed_at.is_some(), working: item.working }))
+        .collect();
+    let mut events = vec![];
+    for (id, before) in myself.state.reported.iter().filter(|(_, before)| before.working) {
+        match current.get(id) {
+            Some(after) if after.working => {},
+            after => {
+                let title = after.unwrap_or(before).title.clone();
+                events.push(AppEvent::WorkingChanged { id: *id, title, working: false });
+            },
         }
     }
-
-    pub fn empty() -> TodoItems {
-        return TodoItems {
-            active_section: Section::TODO,
-            todo_items: vec![],
-            done_items: vec![],
-            removed_items: vec![],
-            highlighted_todo_item: 0,
-            highlighted_done_item: 0,
+    for (id, after) in current.iter() {
+        let before = myself.state.reported.get(id);
+        match before {
+            Some(before) if !before.done && after.done => { events.push(AppEvent::TodoCompleted { id: *id, title: after.title.clone() }) },
+            Some(before) if before.done && !after.done => { events.push(AppEvent::TodoReopened { id: *id, title: after.title.clone() }) },
+            _ => {},
+        }
+        if after.working && before.is_none_or(|before| !before.working || before.title != after.title) {
+            events.push(AppEvent::WorkingChanged { id: *id, title: after.title.clone(), working: true });
         }
     }
-
-    pub fn next_item(&mut self) {
-        if self.active_section == Section::TODO {
-            if self.todo_items.len() > 0 && self.highlighted_todo_item < self.todo_items.len()-1 {
-                self.highlighted_todo_item += 1;
-            };
-        } else {
-            if self.done_items.len() > 0 && self.highlighted_done_item < self.done_items.len()-1 {
-                self.highlighted_done_item += 1;
-            };
-        }; 
+    myself.state.reported = current;
+    for event in events {
+        myself.publish(event);
     }
+}
 
-    pub fn prev_item(&mut self) {
-        if self.active_section == Section::TODO {
+// Called after every change, which is also when other widgets hear about
+// it. Once loading has failed or the store turns out
+// to be corrupt we stop saving, so it's left for the user to look at instead
+// of being replaced. Other failures are retried on the next change.
+fn save_items(myself: &mut Widget<TodoState>) {
+    publish_changes(myself);
+    if myself.state.storage_error.is_some() {
+        return;
+    };
+    let result = myself.state.store.save(&myself.state.items);
+    record_save(myself, result);
+}
 
-            if self.highlighted_todo_item > 0 {
-                self.highlighted_todo_item -= 1;
-            };
-        } else {
-            if self.highlighted_done_item > 0 {
-                self.highlighted_done_item -= 1;
-            };
-        }; 
+// A new item goes on the end of the TODO section, so it's appended to the
+// store rather than rewriting everything. If the last save failed the whole
+// list is written instead, so that change isn't lost.
+fn save_new_item(myself: &mut Widget<TodoState>) {
+    if myself.state.save_error.is_some() || myself.state.storage_error.is_some() {
+        return save_items(myself);
     }
+    publish_changes(myself);
+    let item = match myself.state.items.todo_items.last() {
+        Some(item) => item.clone(),
+        None => return,
+    };
+    let result = myself.state.store.append(&item);
+    record_save(myself, result);
+}
 
-    pub fn toggle_section(&mut self) {
-        if self.active_section == Section::TODO {
-            self.active_section = Section::DONE;
-        } else {
-            self.active_section = Section::TODO;
-        }
-    }
+fn record_save(myself: &mut Widget<TodoState>, result: Result<(), StorageError>) {
+    myself.state.save_error = match result {
+        Ok(()) => None,
+        Err(error @ StorageError::Corrupt(_)) => {
+            myself.state.storage_error = Some(format!("Not saving, couldn't write {}: {}", myself.state.store.describe(), error));
+            None
+        },
+        Err(error) => Some(format!("Couldn't save to {}, will retry: {}", myself.state.store.describe(), error)),
+    };
+}
 
-    pub fn remove_selected_item(&mut self) {
-        if self.active_section == Section::TODO && self.todo_items.len() > 0 {
-            let mut item = self.todo_items.remove(self.highlighted_todo_item);
-            item.working = false;
-            self.removed_items.push(item);
-            if self.todo_items.len() > 0 && self.highlighted_todo_item > self.todo_items.len()-1 {
-                self.highlighted_todo_item -= 1;
-            }
-        } else if self.active_section == Section::DONE && self.done_items.len() > 0 {
-            let item = self.done_items.remove(self.highlighted_done_item);
-            self.removed_items.push(item);
-            if self.done_items.len() > 0 && self.highlighted_done_item > self.done_items.len()-1 {
-                self.highlighted_done_item -= 1;
-            }
+// Writes the marked items to a file, typed as "<format> <file>" in any
+// format `termod export` takes. Relative paths are from where termod started.
+fn export_marked(items: &TodoItems, text: &str) -> Result<String, String> {
+    let (format, path) = match text.split_once(char::is_whitespace) {
+        Some((format, path)) if path.trim().len() > 0 => (format, path.trim()),
+        _ => return Err("type a format and a file, e.g. markdown marked.md".to_string()),
+    };
+    let marked = TodoItems::new(items.marked_items().into_iter().cloned().collect());
+    let exported = export::write(format, &marked, chrono::Utc::now())?;
+    std::fs::write(path, exported).map_err(|error| format!("couldn't write {}: {}", path, error))?;
+    return Ok(format!("Exported {} todos to {}", marked.todo_items.len() + marked.done_items.len(), path));
+}
+
+// Searching narrows both sections as the query is typed. Up and Down move
+// through the matches, Enter keeps the selection and Esc puts it back.
+fn search_event(myself: &mut Widget<TodoState>, event_to_handle: &event::Event) -> EventResult {
+    if let event::Event::Key(event) = event_to_handle {
+        match event.code {
+            event::KeyCode::Up => { myself.state.items.prev_item() },
+            event::KeyCode::Down => { myself.state.items.next_item() },
+            event::KeyCode::Left | event::KeyCode::Right => {
+                myself.state.items.toggle_section();
+                follow_active_section(myself);
+            },
+            event::KeyCode::Enter | event::KeyCode::Esc => {
+                if event.code == event::KeyCode::Esc {
+                    let (section, todo_index, done_index) = myself.state.selection_before_search;
+                    myself.state.items.active_section = section;
+                    myself.state.items.highlighted_todo_item = todo_index;
+                    myself.state.items.highlighted_done_item = done_index;
+                }
+                let filter = Filter { search: None, ..myself.state.items.filter.clone() };
+                myself.state.items.set_filter(filter);
+                current_input(myself).submit();
+                myself.state.input_target = None;
+                follow_active_section(myself);
+            },
+            _ => {
+                current_input(myself).handle_event(event_to_handle);
+                let filter = Filter { search: Some(current_input(myself).text()), ..myself.state.items.filter.clone() };
+                myself.state.items.set_filter(filter);
+                // Show whichever section has matches.
+                let active = myself.state.items.active_section;
+                let other = if active == Section::TODO { Section::DONE } else { Section::TODO };
+                if myself.state.items.visible(active).len() == 0 && myself.state.items.visible(other).len() > 0 {
+                    myself.state.items.toggle_section();
+                    follow_active_section(myself);
+                }
+            },
         }
     }
+    return EventResult::Consumed;
+}
 
-    pub fn toggle_selected_item_completeness(&mut self) {
-        if self.active_section == Section::TODO && self.todo_items.len() > 0 {
-            let mut item = self.todo_items.remove(self.highlighted_todo_item);
-            item.working = false;
-            item.completed_at = Some(chrono::Utc::now());
-            self.done_items.insert(0, item);
-            if self.todo_items.len() > 0 && self.highlighted_todo_item > self.todo_items.len()-1 {
-                self.highlighted_todo_item -= 1;
-            }
-        } else if self.active_section == Section::DONE && self.done_items.len() > 0 {
-            let mut item = self.done_items.remove(self.highlighted_done_item);
-            item.completed_at = None;
-            self.todo_items.push(item);
-            if self.done_items.len() > 0 && self.highlighted_done_item > self.done_items.len()-1 {
-                self.highlighted_done_item -= 1;
-            }
-        }
+// While adding or editing an item every key goes to the input line, so Tab
+// and Esc don't reach the app.
+fn input_event(myself: &mut Widget<TodoState>, event_to_handle: &event::Event) -> EventResult {
+    myself.state.input_error = None;
+    if current_input(myself).handle_event(event_to_handle) == EventResult::Consumed {
+        return EventResult::Consumed;
     }
 
-    pub fn toggle_selected_item_working(&mut self) {
-        if self.active_section == Section::TODO {
-            let item: &mut TodoItem = &mut self.todo_items[self.highlighted_todo_item];
-            item.working = !item.working;
-        }
+    match event_to_handle {
+        event::Event::Key(event) => {
+            match event.code {
+                event::KeyCode::Esc => {
+                    current_input(myself).clear();
+                    myself.state.input_target = None;
+                },
+                event::KeyCode::Enter => {
+                    let text = current_input(myself).text().trim().to_string();
+                    let target = myself.state.input_target.unwrap();
+                    let is_new_item = target == InputTarget::NewItem && text.len() > 0;
+                    let items = &mut myself.state.items;
+                    match target {
+                        InputTarget::NewItem if text.len() > 0 => { items.add_item(text) },
+                        InputTarget::NewChild if text.len() > 0 => { items.add_child_to_selected_item(text) },
+                        InputTarget::EditItem { section, index, field: ItemField::Title } if text.len() > 0 => {
+                            items.rename_item(section, index, text)
+                        },
+                        InputTarget::EditItem { section, index, field: ItemField::Tags } => {
+                            items.set_item_tags(section, index, todo::parse_tags(&text))
+                        },
+                        InputTarget::EditItem { section, index, field: ItemField::Due } => {
+                            match todo::parse_due(&text, chrono::Local::now()) {
+                                Ok(due_at) => { items.set_item_due(section, index, due_at) },
+                                Err(error) => {
+                                    myself.state.input_error = Some(error);
+                                    return EventResult::Consumed;
+                                },
+                            }
+                        },
+                        InputTarget::EditItem { section, index, field: ItemField::Recurrence } => {
+                            match Recurrence::parse(&text) {
+                                Ok(recurrence) => { items.edit_item(section, index, |item| item.recurrence = recurrence) },
+                                Err(error) => {
+                                    myself.state.input_error = Some(error);
+                                    return EventResult::Consumed;
+                                },
+                            }
+                        },
+                        InputTarget::MarkedTags => { items.tag_marked_items(&todo::parse_tags(&text)) },
+                        InputTarget::MarkedExport => {
+                            match export_marked(items, &text) {
+                                Ok(notice) => { myself.state.notice = Some(notice) },
+                                Err(error) => {
+                                    myself.state.input_error = Some(error);
+                                    return EventResult::Consumed;
+                                },
+                            }
+                        },
+                        InputTarget::ReportRange => {
+                            match report::DateRange::parse(&text, chrono::Local::now().date_naive()) {
+                                Ok(range) => {
+                                    let markdown = report::standup_report(&myself.state.items, range, chrono::Utc::now());
+                                    let lines = markdown.lines().map(|line| line.to_string()).collect();
+                                    let report_view = text_view_widget::new(myself.width, myself.height, "Report", lines);
+                                    myself.push_view(Box::new(report_view), false);
+                                },
+                                Err(error) => {
+                                    myself.state.input_error = Some(error);
+                                    return EventResult::Consumed;
+                                },
+                            }
+                        },
+                        InputTarget::Filter => {
+                            let tag = todo::parse_tags(&text).into_iter().next();
+                            items.set_filter(Filter { tag, ..items.filter.clone() });
+                        },
+                        _ => {},
+                    }
+                    current_input(myself).submit();
+                    myself.state.input_target = None;
+                    if is_new_item {
+                        save_new_item(myself);
+                    } else {
+                        save_items(myself);
+                    }
+                },
+                _ => {}
+            }
+        },
+        _ => {}
     }
+    return EventResult::Consumed;
+}
 
-    pub fn undo_last_remove(&mut self) {
-        if self.removed_items.len() < 1 {
-            return;
+// Opens the input over the highlighted item, filled with the field's current value.
+fn start_editing(myself: &mut Widget<TodoState>, field: ItemField) {
+    let section = myself.state.items.active_section;
+    let index = if section == Section::TODO {
+        myself.state.items.highlighted_todo_item
+    } else {
+        myself.state.items.highlighted_done_item
+    };
+    if let Some(item) = myself.state.items.selected_item() {
+        let text = match field {
+            ItemField::Title => item.title.clone(),
+            ItemField::Tags => item.tags.join(" "),
+            ItemField::Due => match item.due_at {
+                Some(due_at) => due_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string(),
+                None => "".to_string(),
+            },
+            ItemField::Recurrence => item.recurrence.as_ref().map(|recurrence| recurrence.describe()).unwrap_or_default(),
         };
-
-        let item = self.removed_items.pop().unwrap();
-        if item.completed_at == None {
-            self.todo_items.push(item);
-        } else {
-            self.done_items.push(item);
-        }
+        open_input(myself, InputTarget::EditItem { section, index, field }, &text);
     }
 }
 
-pub struct TodoState {
-    main_buffer: Buffer,
-    update_main_buffer: bool,
-    items: TodoItems,
-    item_seperator_height: usize,
-    ages_last_calculated_at: std::time::Instant,
+// The input for the prompt that's open.
+fn current_input(myself: &mut Widget<TodoState>) -> &mut TextInput {
+    let target = myself.state.input_target.unwrap();
+    return input_for(myself, target);
 }
 
-impl WidgetTrait for Widget<TodoState> {
-    fn init(&mut self) {self.init()}
-    fn handle_event(&mut self, event_to_handle: &event::Event) {self.handle_event(event_to_handle)}
-    fn update(&mut self) {self.update()}
-    fn draw(&mut self) {self.draw()}
-    fn generate_buffer(&mut self) -> Buffer {self.generate_buffer()}
-    fn get_title(&self) -> &String {&self.title}
+fn input_for(myself: &mut Widget<TodoState>, target: InputTarget) -> &mut TextInput {
+    let width = myself.width.saturating_sub(2);
+    return myself.state.inputs.entry(target.prompt()).or_insert_with(|| TextInput::new(width).with_placeholder(target.prompt()));
 }
 
-fn init(myself: &mut Widget<TodoState>) {
-    myself.state.items = TodoItems::new(vec![
-        TodoItem { 
-            title: "Buy groceries".to_string(),
-            added_at: chrono::Utc::now(),
-            completed_at: None,
-            working: false
-        },
-        TodoItem { 
-            title: "Answer emails".to_string(), 
-            added_at: chrono::DateTime::parse_from_rfc3339("2024-04-01T13:39:57Z").unwrap().to_utc(),
-            completed_at: None,
-            working: false
-        },
-        TodoItem { 
-            title: "Write code".to_string(), 
-            added_at: chrono::DateTime::parse_from_rfc3339("2023-04-01T13:39:57Z").unwrap().to_utc(),
-            completed_at: Some(chrono::Utc::now() - std::time::Duration::from_secs(100)), 
-            working: false 
-        },
-        TodoItem { 
-            title: "Feed dogs".to_string(), 
-            added_at: chrono::DateTime::parse_from_rfc3339("2024-04-01T13:39:57Z").unwrap().to_utc(),
-            completed_at: Some(chrono::Utc::now()), 
-            working: false 
-        },
-    ]);
+// Opens a prompt with `text` already typed.
+fn open_input(myself: &mut Widget<TodoState>, target: InputTarget, text: &str) {
+    input_for(myself, target).set_text(text);
+    myself.state.input_target = Some(target);
 }
 
-fn event(myself: &mut Widget<TodoState>, event_to_handle: &event::Event) {
+// The active section gets the larger share of the rows.
+fn follow_active_section(myself: &mut Widget<TodoState>) {
+    myself.state.item_seperator_height = if myself.state.items.active_section == Section::TODO {
+        (myself.height as usize).saturating_sub(10)
+    } else {
+        10
+    };
+}
+
+fn event(myself: &mut Widget<TodoState>, event_to_handle: &event::Event) -> EventResult {
     myself.state.update_main_buffer = true;
+    if myself.state.input_target == Some(InputTarget::Search) {
+        return search_event(myself, event_to_handle);
+    } else if myself.state.input_target.is_some() {
+        return input_event(myself, event_to_handle);
+    } else if myself.state.visual {
+        return visual_event(myself, event_to_handle);
+    }
+
+    // A message from the last key, e.g. why an item couldn't be moved.
+    myself.state.input_error = None;
+    myself.state.notice = None;
+    let mut changed = false;
     match event_to_handle {
         event::Event::Key(event) => {
             match event.code {
+                // Keyboard only: the app doesn't capture the mouse, so there's
+                // no dragging items.
+                event::KeyCode::Up if event.modifiers.contains(event::KeyModifiers::SHIFT) => {
+                    changed = true;
+                    myself.state.input_error = myself.state.items.move_selected_item_up().err();
+                },
+                event::KeyCode::Down if event.modifiers.contains(event::KeyModifiers::SHIFT) => {
+                    changed = true;
+                    myself.state.input_error = myself.state.items.move_selected_item_down().err();
+                },
                 event::KeyCode::Up => { myself.state.items.prev_item() },
                 event::KeyCode::Down => { myself.state.items.next_item() },
                 event::KeyCode::Right | event::KeyCode::Left => { 
                     myself.state.items.toggle_section();
-                    myself.state.item_seperator_height = myself.height as usize - myself.state.item_seperator_height;
+                    follow_active_section(myself);
                 },
-                event::KeyCode::Enter => { myself.state.items.toggle_selected_item_completeness() },
-                event::KeyCode::Char('w') => { myself.state.items.toggle_selected_item_working() },
-                event::KeyCode::Char('d') => { myself.state.items.remove_selected_item() },
-                event::KeyCode::Char('u') => { myself.state.items.undo_last_remove() },
-                _ => {}
+                event::KeyCode::Enter => {
+                    changed = true;
+                    myself.state.items.toggle_selected_item_completeness();
+                },
+                event::KeyCode::Char('w') => {
+                    changed = true;
+                    myself.state.items.toggle_selected_item_working();
+                },
+                event::KeyCode::Char('d') => {
+                    changed = true;
+                    myself.state.items.remove_selected_item();
+                },
+                event::KeyCode::Char('r') if event.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                    changed = true;
+                    myself.state.items.redo();
+                    follow_active_section(myself);
+                },
+                event::KeyCode::Char('u') => {
+                    changed = true;
+                    myself.state.items.undo();
+                    follow_active_section(myself);
+                },
+                event::KeyCode::Char('a') => {
+                    if myself.state.items.active_section != Section::TODO {
+                        myself.state.items.toggle_section();
+                        follow_active_section(myself);
+                    }
+                    open_input(myself, InputTarget::NewItem, "");
+                },
+                event::KeyCode::Char('A') => {
+                    if myself.state.items.active_section == Section::TODO && myself.state.items.selected_item().is_some() {
+                        open_input(myself, InputTarget::NewChild, "");
+                    }
+                },
+                event::KeyCode::Char('X') => {
+                    changed = true;
+                    myself.state.items.complete_selected_item_with_children();
+                },
+                event::KeyCode::Char('c') => {
+                    changed = true;
+                    myself.state.items.toggle_selected_collapsed();
+                },
+                event::KeyCode::Char('v') => {
+                    myself.state.visual = true;
+                    myself.state.items.mark_selected_item();
+                },
+                event::KeyCode::Char(' ') => {
+                    myself.state.visual = true;
+                    myself.state.items.toggle_selected_marked();
+                },
+                event::KeyCode::Char('e') => { start_editing(myself, ItemField::Title) },
+                event::KeyCode::Char('t') => { start_editing(myself, ItemField::Tags) },
+                event::KeyCode::Char('D') => { start_editing(myself, ItemField::Due) },
+                event::KeyCode::Char('r') => { start_editing(myself, ItemField::Recurrence) },
+                event::KeyCode::Char('s') => {
+                    let section = myself.state.items.active_section;
+                    myself.state.items.cycle_sort_mode(section);
+                },
+                event::KeyCode::Char('f') => {
+                    let tag = myself.state.items.filter.tag.clone().unwrap_or_default();
+                    open_input(myself, InputTarget::Filter, &tag);
+                },
+                event::KeyCode::Char('/') => {
+                    let items = &myself.state.items;
+                    myself.state.selection_before_search = (items.active_section, items.highlighted_todo_item, items.highlighted_done_item);
+                    open_input(myself, InputTarget::Search, "");
+                },
+                event::KeyCode::Char('!') => {
+                    let filter = Filter { overdue: !myself.state.items.filter.overdue, ..myself.state.items.filter.clone() };
+                    myself.state.items.set_filter(filter);
+                },
+                event::KeyCode::Char('W') => {
+                    let filter = Filter { working: !myself.state.items.filter.working, ..myself.state.items.filter.clone() };
+                    myself.state.items.set_filter(filter);
+                },
+                event::KeyCode::Char('p') => {
+                    changed = true;
+                    myself.state.items.cycle_selected_item_priority();
+                },
+                event::KeyCode::Char('o') => {
+                    if let Some(item) = myself.state.items.selected_item() {
+                        let detail = text_view_widget::new(myself.width, myself.height, &item.title, item.detail_lines());
+                        myself.push_view(Box::new(detail), false);
+                    }
+                },
+                event::KeyCode::Char('R') => {
+                    open_input(myself, InputTarget::ReportRange, "yesterday..today");
+                },
+                event::KeyCode::Char('?') => {
+                    let help = text_view_widget::new(36, 12, "Help", help_lines());
+                    myself.push_view(Box::new(help), true);
+                },
+                _ => { return EventResult::Unhandled }
             }
+            if changed {
+                save_items(myself);
+            }
+            return EventResult::Consumed;
         },
-        _ => {}
+        _ => { return EventResult::Unhandled }
     }
 }
 
+// While items are marked, Space marks or unmarks the highlighted one and
+// Shift-Up/Down mark everything moved over. Completing, reopening and
+// deleting act on the marked items and end marking, as do Esc and v.
+fn visual_event(myself: &mut Widget<TodoState>, event_to_handle: &event::Event) -> EventResult {
+    let event = match event_to_handle {
+        event::Event::Key(event) => event,
+        _ => return EventResult::Unhandled,
+    };
+    myself.state.notice = None;
+    let items = &mut myself.state.items;
+    let mut changed = false;
+    match event.code {
+        event::KeyCode::Up if event.modifiers.contains(event::KeyModifiers::SHIFT) => {
+            items.mark_selected_item();
+            items.prev_item();
+            items.mark_selected_item();
+        },
+        event::KeyCode::Down if event.modifiers.contains(event::KeyModifiers::SHIFT) => {
+            items.mark_selected_item();
+            items.next_item();
+            items.mark_selected_item();
+        },
+        event::KeyCode::Up => { items.prev_item() },
+        event::KeyCode::Down => { items.next_item() },
+        event::KeyCode::Right | event::KeyCode::Left => {
+            items.toggle_section();
+            follow_active_section(myself);
+        },
+        event::KeyCode::Char(' ') => { items.toggle_selected_marked() },
+        event::KeyCode::Enter => {
+            changed = true;
+            if items.active_section == Section::TODO {
+                items.complete_marked_items();
+            } else {
+                items.reopen_marked_items();
+            }
+            myself.state.visual = false;
+        },
+        event::KeyCode::Char('d') => {
+            changed = true;
+            items.remove_marked_items();
+            myself.state.visual = false;
+        },
+        event::KeyCode::Char('t') => {
+            open_input(myself, InputTarget::MarkedTags, "");
+        },
+        event::KeyCode::Char('m') => {
+            open_input(myself, InputTarget::MarkedExport, "markdown marked.md");
+        },
+        event::KeyCode::Esc | event::KeyCode::Char('v') => {
+            items.marked.clear();
+            myself.state.visual = false;
+        },
+        _ => { return EventResult::Unhandled },
+    }
+    if changed {
+        save_items(myself);
+    }
+    return EventResult::Consumed;
+}
+
 fn update(myself: &mut Widget<TodoState>) {
     if std::time::Instant::now() - myself.state.ages_last_calculated_at > std::time::Duration::from_secs(1) {
         myself.state.update_main_buffer = true;
@@ -231,78 +623,201 @@ fn update(myself: &mut Widget<TodoState>) {
     };
 }
 
+// "<age> [worked] [due ..] - ▾ (A) title [2/5] #tag", indented by `depth`. Overdue
+// items have their due date and title in red, items due soon a '!' before
+// the due date. Parents get a collapse marker and their subtask progress.
+fn item_row(items: &TodoItems, item: &TodoItem, depth: usize, attributes: Option<style::Attributes>, now: chrono::DateTime<chrono::Utc>, width: usize) -> Vec<character::Character> {
+    let search = items.filter.search.as_deref();
+    // Open items show their age, finished ones how long they took.
+    let (age, title_colour) = match item.completed_at {
+        None => (format_duration(item.calculate_age()), if item.working { Some(colours::ORANGE) } else { None }),
+        Some(completed_at) => (format_duration(completed_at - item.added_at), None),
+    };
+    let mut chars = character::Character::vec_from_string(&age, Some(colours::GREY), None);
+    if item.sessions.len() > 0 {
+        let worked = format!(" {}", todo::format_worked(item.time_worked(now)));
+        let colour = if item.working { colours::ORANGE } else { colours::LIGHT_BLUE };
+        chars.append(&mut character::Character::vec_from_string(&worked, Some(colour), None));
+    }
+    let due_state = item.due_state(now);
+    if let Some(due_at) = item.due_at {
+        let (due_text, due_colour) = match due_state {
+            Some(DueState::Overdue) => (format!(" {}", todo::format_due(due_at)), colours::RED),
+            Some(DueState::DueSoon) => (format!(" !{}", todo::format_due(due_at)), colours::YELLOW),
+            _ => (format!(" {}", todo::format_due(due_at)), colours::GREY),
+        };
+        chars.append(&mut character::Character::vec_from_string(&due_text, Some(due_colour), None));
+    }
+    chars.append(&mut character::Character::vec_from_string(" - ", Some(colours::GREY), None));
+    if items.is_marked(item) {
+        chars.append(&mut character::Character::vec_from_string("* ", Some(colours::LIGHT_BLUE), None));
+    }
+    let marker = match items.progress(item) {
+        Some(_) if item.collapsed => "▸ ",
+        Some(_) => "▾ ",
+        None => "",
+    };
+    chars.append(&mut character::Character::vec_from_string(&format!("{}{}", "  ".repeat(depth), marker), Some(colours::GREY), None));
+    if let Some(priority) = item.priority {
+        chars.append(&mut character::Character::vec_from_string(&format!("({}) ", priority), Some(colours::LIGHT_BLUE), attributes));
+    }
+    if item.recurrence.is_some() {
+        chars.append(&mut character::Character::vec_from_string("↻ ", Some(colours::GREY), None));
+    }
+    let title_colour = if due_state == Some(DueState::Overdue) { Some(colours::RED) } else { title_colour };
+    let mut title_chars = character::Character::vec_from_string(&item.title, title_colour, attributes);
+    highlight_matches(&mut title_chars, search, &item.title);
+    chars.append(&mut title_chars);
+    if let Some((done, total)) = items.progress(item) {
+        let colour = if done == total { colours::LIGHT_BLUE } else { colours::GREY };
+        chars.append(&mut character::Character::vec_from_string(&format!(" {}/{}", done, total), Some(colour), None));
+    }
+    for tag in &item.tags {
+        let mut tag_chars = character::Character::vec_from_string(tag, Some(colours::GREY), None);
+        highlight_matches(&mut tag_chars, search, tag);
+        chars.append(&mut character::Character::vec_from_string(" #", Some(colours::GREY), None));
+        chars.append(&mut tag_chars);
+    }
+    chars.truncate(width);
+    return chars;
+}
+
+fn highlight_matches(chars: &mut [character::Character], search: Option<&str>, text: &str) {
+    let positions = match search.and_then(|search| todo::fuzzy_match(search, text)) {
+        Some(positions) => positions,
+        None => return,
+    };
+    for position in positions {
+        chars[position].colour = colours::YELLOW;
+        chars[position].attributes.set(style::Attribute::Underlined);
+    }
+}
+
+fn format_duration(duration: chrono::TimeDelta) -> String {
+    let days = duration.num_days();
+    let hours = (duration - chrono::TimeDelta::days(days)).num_hours();
+    let minutes = (duration - chrono::TimeDelta::days(days) - chrono::TimeDelta::hours(hours)).num_minutes();
+    let seconds = (duration - chrono::TimeDelta::days(days) - chrono::TimeDelta::hours(hours) - chrono::TimeDelta::minutes(minutes)).num_seconds();
+    return format!("{:0>3}:{:0>2}:{:0>2}:{:0>2}", days, hours, minutes, seconds);
+}
+
+// The first row and number of rows each section is drawn in.
+fn section_rows(myself: &Widget<TodoState>, section: Section) -> (usize, usize) {
+    let seperator = myself.state.item_seperator_height.min(myself.height as usize);
+    return match section {
+        Section::TODO => (HEADER_ROWS, seperator.saturating_sub(HEADER_ROWS)),
+        Section::DONE => (seperator + 1, (myself.height as usize).saturating_sub(seperator + 1)),
+    };
+}
+
+// How many of the section's shown items are scrolled past, enough to keep
+// the highlighted one on screen.
+fn scroll_offset(myself: &Widget<TodoState>, section: Section, visible: &[usize]) -> usize {
+    let (_, rows) = section_rows(myself, section);
+    let highlighted = myself.state.items.highlighted(section);
+    let position = visible.iter().position(|i| *i == highlighted).unwrap_or(0);
+    return (position + 1).saturating_sub(rows.max(1));
+}
+
+// The row an item is drawn on, if it's on screen.
+fn row_of(myself: &Widget<TodoState>, section: Section, index: usize) -> Option<usize> {
+    let visible = myself.state.items.visible(section);
+    let (first_row, rows) = section_rows(myself, section);
+    let position = visible.iter().position(|i| *i == index)?;
+    let offset = scroll_offset(myself, section, &visible);
+    if position < offset || position - offset >= rows {
+        return None;
+    }
+    return Some(first_row + position - offset);
+}
+
+fn header_text(items: &TodoItems, visual: bool) -> String {
+    let mut header = format!(
+        "TODO {}/{} sort:{}  DONE {}/{} sort:{}",
+        items.visible(Section::TODO).len(),
+        items.todo_items.len(),
+        items.todo_sort.name(),
+        items.visible(Section::DONE).len(),
+        items.done_items.len(),
+        items.done_sort.name(),
+    );
+    if !items.filter.is_empty() {
+        header.push_str(&format!("  filter: {}", items.filter.describe()));
+    }
+    if visual {
+        header.push_str(&format!("  marked: {}", items.marked.len()));
+    }
+    return header;
+}
+
 fn draw(myself: &mut Widget<TodoState>) {
     if !myself.state.update_main_buffer { return };
-   
-    let all_items: &TodoItems = &myself.state.items;
+
+    let now = chrono::Utc::now();
+    let width = myself.width as usize;
     myself.state.main_buffer.clear();
-    // Draw todo items
-    let mut i = 0;
-    for item in &all_items.todo_items {
-        let attributes= if all_items.active_section == Section::TODO && all_items.highlighted_todo_item == i { 
-            Some(style::Attributes::from(style::Attribute::Reverse)) 
-        } else { 
-            None 
-        };
 
-        let age: chrono::TimeDelta = item.calculate_age();
-        let age_days= age.num_days();
-        let age_hours= (age - chrono::TimeDelta::days(age_days)).num_hours();
-        let age_minutes= (age - chrono::TimeDelta::days(age_days) - chrono::TimeDelta::hours(age_hours)).num_minutes();
-        let age_seconds = (age - chrono::TimeDelta::days(age_days) - chrono::TimeDelta::hours(age_hours) - chrono::TimeDelta::minutes(age_minutes)).num_seconds();
-        let mut title_prefix_chars= character::Character::vec_from_string(
-            &format!("{:0>3}:{:0>2}:{:0>2}:{:0>2} - ", age_days, age_hours, age_minutes, age_seconds), 
-            Some(colours::GREY), 
-            None,
-        );
-        let mut title_chars = character::Character::vec_from_string(
-            &item.title, 
-            if item.working { Some(colours::ORANGE) } else { None }, 
-            attributes
-        );
-        title_prefix_chars.append(&mut title_chars);
-        myself.state.main_buffer.insert_char_slice(i * myself.width as usize, &title_prefix_chars);
-        i += 1;
-    };
+    let header = header_text(&myself.state.items, myself.state.visual).chars().take(width).collect::<String>();
+    let header_colour = if myself.state.items.filter.is_empty() && !myself.state.visual { colours::GREY } else { colours::LIGHT_BLUE };
+    myself.state.main_buffer.insert_char_slice(0, &character::Character::vec_from_string(&header, Some(header_colour), None));
 
-    let seperator_string = "-".repeat(myself.width as usize);
-    let seperator_chars = character::Character::vec_from_string(&seperator_string, None, None);
-    myself.state.main_buffer.insert_char_slice(myself.state.item_seperator_height * myself.width as usize, &seperator_chars);
-
-    // Draw done items
-    let mut i = 0;
-    for item in &all_items.done_items {
-        let attributes= if all_items.active_section == Section::DONE && all_items.highlighted_done_item == i { 
-            Some(style::Attributes::from(style::Attribute::Reverse)) 
-        } else { 
-            None 
-        };
+    for section in [Section::TODO, Section::DONE] {
+        let tree = myself.state.items.visible_tree(section);
+        let visible: Vec<usize> = tree.iter().map(|(index, _)| *index).collect();
+        let (first_row, rows) = section_rows(myself, section);
+        let offset = scroll_offset(myself, section, &visible);
+        let all_items: &TodoItems = &myself.state.items;
+        for (row, (index, depth)) in tree.iter().skip(offset).take(rows).enumerate() {
+            let item = &all_items.section_items(section)[*index];
+            let attributes = if all_items.active_section == section && all_items.highlighted(section) == *index {
+                Some(style::Attributes::from(style::Attribute::Reverse))
+            } else {
+                None
+            };
+            let row_chars = item_row(all_items, item, *depth, attributes, now, width);
+            myself.state.main_buffer.insert_char_slice((first_row + row) * width, &row_chars);
+        }
+    }
 
-        let ttc: chrono::TimeDelta = item.completed_at.unwrap() - item.added_at;
-        let ttc_days= ttc.num_days();
-        let ttc_hours= (ttc- chrono::TimeDelta::days(ttc_days)).num_hours();
-        let ttc_minutes= (ttc- chrono::TimeDelta::days(ttc_days) - chrono::TimeDelta::hours(ttc_hours)).num_minutes();
-        let ttc_seconds = (ttc- chrono::TimeDelta::days(ttc_days) - chrono::TimeDelta::hours(ttc_hours) - chrono::TimeDelta::minutes(ttc_minutes)).num_seconds();
-        
-        let mut title_prefix_chars= character::Character::vec_from_string(
-            &format!("{:0>3}:{:0>2}:{:0>2}:{:0>2} - ", ttc_days, ttc_hours, ttc_minutes, ttc_seconds), 
-            Some(colours::GREY), 
-            None,
-        );
-        let mut title_chars = character::Character::vec_from_string(
-            &item.title,
-            None, 
-            attributes
-        );
-
-        title_prefix_chars.append(&mut title_chars);
-
-        let line_number = i + myself.state.item_seperator_height + 1;
-        if line_number < myself.height as usize {
-            myself.state.main_buffer.insert_char_slice(line_number * myself.width as usize, &title_prefix_chars);
+    // Widgets shorter than the DONE section's share have no room for it.
+    if myself.state.item_seperator_height < myself.height as usize {
+        let seperator_string = "-".repeat(width);
+        let seperator_chars = character::Character::vec_from_string(&seperator_string, None, None);
+        myself.state.main_buffer.insert_char_slice(myself.state.item_seperator_height * width, &seperator_chars);
+    }
+
+    // New items are typed on the last line of the TODO section, edits over
+    // the item being edited and filters over the header.
+    myself.cursor_position = None;
+    if let Some(target) = myself.state.input_target {
+        let input_line = match target {
+            InputTarget::NewItem | InputTarget::NewChild => Some(myself.state.item_seperator_height.saturating_sub(1)),
+            InputTarget::EditItem { section, index, .. } => row_of(myself, section, index),
+            InputTarget::Filter | InputTarget::Search | InputTarget::MarkedTags | InputTarget::MarkedExport | InputTarget::ReportRange => Some(0),
+        };
+        if let Some(input_line) = input_line.filter(|line| *line < myself.height as usize) {
+            let prompt = match target {
+                InputTarget::Filter | InputTarget::MarkedTags => "# ",
+                InputTarget::Search => "/ ",
+                _ => "> ",
+            };
+            let prompt_chars = character::Character::vec_from_string(prompt, Some(colours::LIGHT_BLUE), None);
+            myself.state.main_buffer.insert_char_slice(input_line * width, &prompt_chars);
+            let input = current_input(myself);
+            let (rendered, cursor_column) = (input.render(), input.cursor_column());
+            myself.state.main_buffer.blit(2, input_line as u16, &rendered);
+            myself.cursor_position = Some((2 + cursor_column, input_line as u16));
         }
-        i += 1;
-    };
+    }
+
+    let error = myself.state.input_error.as_ref().or(myself.state.storage_error.as_ref()).or(myself.state.save_error.as_ref());
+    if let Some(error) = error {
+        let error_chars = character::Character::vec_from_string(&error.chars().take(width).collect::<String>(), Some(colours::RED), None);
+        myself.state.main_buffer.insert_char_slice((myself.height as usize).saturating_sub(1) * width, &error_chars);
+    } else if let Some(notice) = &myself.state.notice {
+        let notice_chars = character::Character::vec_from_string(&notice.chars().take(width).collect::<String>(), Some(colours::LIGHT_BLUE), None);
+        myself.state.main_buffer.insert_char_slice((myself.height as usize).saturating_sub(1) * width, &notice_chars);
+    }
 
     myself.state.update_main_buffer = false;
 }
@@ -311,22 +826,548 @@ fn generate_buffer(myself: &mut Widget<TodoState>) -> Buffer {
     return myself.state.main_buffer.clone();
 }
 
-pub fn new(width: u16, height: u16, _x: usize, _y: usize) -> Widget<TodoState> {
+pub fn new(width: u16, height: u16, _x: usize, _y: usize, store: Box<dyn TodoStore>) -> Widget<TodoState> {
     return Widget {
         width,
         height,
         title: "Todo".to_string(),
         init_fn: init,
         event_fn: event,
+        app_event_fn: widget::ignore_app_event,
         update_fn: update,
         draw_fn: draw,
         generate_buffer_fn: generate_buffer,
+        subscriptions: vec![],
+        published_events: vec![],
+        navigation_requests: vec![],
+        cursor_position: None,
         state: TodoState { 
             main_buffer: Buffer::new(width, height),
             update_main_buffer: true,
-            item_seperator_height: height as usize - 10,
+            item_seperator_height: (height as usize).saturating_sub(10),
             items: TodoItems::empty(),
             ages_last_calculated_at: std::time::Instant::now() - std::time::Duration::from_secs(10),
+            store,
+            storage_error: None,
+            save_error: None,
+            inputs: BTreeMap::new(),
+            visual: false,
+            input_target: None,
+            input_error: None,
+            notice: None,
+            selection_before_search: (Section::TODO, 0, 0),
+            reported: BTreeMap::new(),
         }
     };
 }
+
+#[cfg(test)]
+mod event_tests {
+    use super::*;
+    use crate::test_support::titles;
+    use crate::todo_store::MemoryStore;
+
+    fn key(code: event::KeyCode) -> event::Event {
+        return event::Event::Key(event::KeyEvent::new(code, event::KeyModifiers::NONE));
+    }
+
+    fn new_widget() -> Widget<TodoState> {
+        let mut widget = new(60, 20, 0, 0, Box::new(MemoryStore::default()));
+        widget.init();
+        return widget;
+    }
+
+    // The text of each screen row, as drawn now.
+    fn screen_rows(widget: &mut Widget<TodoState>) -> Vec<String> {
+        widget.draw();
+        let buffer = widget.generate_buffer();
+        let width = buffer.width as usize;
+        return (0..buffer.height as usize).map(|y| (0..width).map(|x| buffer[y * width + x].c).collect()).collect();
+    }
+
+    // Fails to save a given number of times, as if the store were busy.
+    struct FlakyStore {
+        failures_left: usize,
+        inner: MemoryStore,
+    }
+
+    impl TodoStore for FlakyStore {
+        fn load(&mut self) -> Result<TodoItems, StorageError> {
+            return self.inner.load();
+        }
+
+        fn save(&mut self, items: &TodoItems) -> Result<(), StorageError> {
+            if self.failures_left > 0 {
+                self.failures_left -= 1;
+                return Err(StorageError::Io(std::io::Error::other("database is locked")));
+            }
+            return self.inner.save(items);
+        }
+
+        fn append(&mut self, item: &TodoItem) -> Result<(), StorageError> {
+            if self.failures_left > 0 {
+                self.failures_left -= 1;
+                return Err(StorageError::Io(std::io::Error::other("database is locked")));
+            }
+            return self.inner.append(item);
+        }
+
+        fn describe(&self) -> String {
+            return "flaky".to_string();
+        }
+    }
+
+    // Can only add items, so any full save fails.
+    struct AppendOnlyStore {
+        inner: MemoryStore,
+    }
+
+    impl TodoStore for AppendOnlyStore {
+        fn load(&mut self) -> Result<TodoItems, StorageError> {
+            return self.inner.load();
+        }
+
+        fn save(&mut self, _items: &TodoItems) -> Result<(), StorageError> {
+            return Err(StorageError::Io(std::io::Error::other("append only")));
+        }
+
+        fn append(&mut self, item: &TodoItem) -> Result<(), StorageError> {
+            return self.inner.append(item);
+        }
+
+        fn describe(&self) -> String {
+            return "append only".to_string();
+        }
+    }
+
+    #[test]
+    fn new_items_are_appended_to_the_store() {
+        let mut widget = new(40, 20, 0, 0, Box::new(AppendOnlyStore { inner: MemoryStore::default() }));
+        widget.init();
+        for title in ["Feed dogs", "Write code"] {
+            widget.handle_event(&key(event::KeyCode::Char('a')));
+            type_text(&mut widget, title);
+            widget.handle_event(&key(event::KeyCode::Enter));
+            assert!(widget.state.save_error.is_none());
+        }
+        assert_eq!(titles(&widget.state.store.load().unwrap().todo_items), vec!["Feed dogs", "Write code"]);
+
+        widget.handle_event(&key(event::KeyCode::Char('p')));
+        assert!(widget.state.save_error.is_some());
+    }
+
+    #[test]
+    fn saving_is_retried_after_a_transient_failure() {
+        let mut widget = new(40, 20, 0, 0, Box::new(FlakyStore { failures_left: 1, inner: MemoryStore::default() }));
+        widget.init();
+        widget.handle_event(&key(event::KeyCode::Char('a')));
+        type_text(&mut widget, "Feed dogs");
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert!(widget.state.save_error.is_some());
+        assert!(widget.state.storage_error.is_none());
+
+        widget.handle_event(&key(event::KeyCode::Char('p')));
+        assert!(widget.state.save_error.is_none());
+        assert_eq!(widget.state.store.load().unwrap().todo_items[0].title, "Feed dogs");
+    }
+
+    #[test]
+    fn short_widgets_still_draw() {
+        for height in [1, 6] {
+            let mut widget = new(40, height, 0, 0, Box::new(MemoryStore::default()));
+            widget.init();
+            widget.state.items.add_item("Feed dogs".to_string());
+            widget.state.items.toggle_selected_item_completeness();
+            widget.state.items.add_item("Write code".to_string());
+            widget.handle_event(&key(event::KeyCode::Right));
+            screen_rows(&mut widget);
+            widget.handle_event(&key(event::KeyCode::Left));
+            assert_eq!(screen_rows(&mut widget).len(), height as usize);
+        }
+    }
+
+    #[test]
+    fn navigation_keys_are_consumed() {
+        let mut widget = new_widget();
+        assert_eq!(widget.handle_event(&key(event::KeyCode::Down)), EventResult::Consumed);
+        assert_eq!(widget.handle_event(&key(event::KeyCode::Char('w'))), EventResult::Consumed);
+    }
+
+    #[test]
+    fn global_shortcut_keys_are_left_unhandled() {
+        let mut widget = new_widget();
+        assert_eq!(widget.handle_event(&key(event::KeyCode::Tab)), EventResult::Unhandled);
+        assert_eq!(widget.handle_event(&key(event::KeyCode::Esc)), EventResult::Unhandled);
+    }
+
+    #[test]
+    fn adding_an_item_consumes_keys_until_committed() {
+        let mut widget = new_widget();
+        widget.handle_event(&key(event::KeyCode::Char('a')));
+        for c in "Feed dgs".chars() {
+            widget.handle_event(&key(event::KeyCode::Char(c)));
+        }
+        widget.handle_event(&key(event::KeyCode::Left));
+        widget.handle_event(&key(event::KeyCode::Left));
+        widget.handle_event(&key(event::KeyCode::Char('o')));
+        assert_eq!(widget.handle_event(&key(event::KeyCode::Tab)), EventResult::Consumed);
+        widget.handle_event(&key(event::KeyCode::Enter));
+
+        assert_eq!(widget.state.items.todo_items.len(), 1);
+        assert_eq!(widget.state.items.todo_items[0].title, "Feed dogs");
+        assert!(widget.state.input_target.is_none());
+    }
+
+    #[test]
+    fn esc_cancels_adding_an_item() {
+        let mut widget = new_widget();
+        widget.handle_event(&key(event::KeyCode::Char('a')));
+        widget.handle_event(&key(event::KeyCode::Char('x')));
+        assert_eq!(widget.handle_event(&key(event::KeyCode::Esc)), EventResult::Consumed);
+
+        assert_eq!(widget.state.items.todo_items.len(), 0);
+        assert!(widget.state.input_target.is_none());
+    }
+
+    #[test]
+    fn editing_renames_the_highlighted_item() {
+        let mut widget = new_widget();
+        widget.state.items.add_item("Feed dgos".to_string());
+        widget.handle_event(&key(event::KeyCode::Char('e')));
+        for _ in 0..4 {
+            widget.handle_event(&key(event::KeyCode::Backspace));
+        }
+        for c in "dogs".chars() {
+            widget.handle_event(&key(event::KeyCode::Char(c)));
+        }
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert_eq!(widget.state.items.todo_items[0].title, "Feed dogs");
+
+        widget.handle_event(&key(event::KeyCode::Char('u')));
+        assert_eq!(widget.state.items.todo_items[0].title, "Feed dgos");
+    }
+
+    fn type_text(widget: &mut Widget<TodoState>, text: &str) {
+        for c in text.chars() {
+            widget.handle_event(&key(event::KeyCode::Char(c)));
+        }
+    }
+
+    #[test]
+    fn tags_and_priority_show_in_the_row() {
+        let mut widget = new_widget();
+        widget.state.items.add_item("Feed dogs".to_string());
+        widget.handle_event(&key(event::KeyCode::Char('p')));
+        widget.handle_event(&key(event::KeyCode::Char('t')));
+        type_text(&mut widget, "home pets");
+        widget.handle_event(&key(event::KeyCode::Enter));
+
+        let rows = screen_rows(&mut widget);
+        assert!(rows[1].contains("- (A) Feed dogs #home #pets"), "{}", rows[1]);
+    }
+
+    #[test]
+    fn an_unreadable_due_date_keeps_the_input_open() {
+        let mut widget = new_widget();
+        widget.state.items.add_item("Feed dogs".to_string());
+        widget.handle_event(&key(event::KeyCode::Char('D')));
+        type_text(&mut widget, "someday");
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert!(widget.state.input_target.is_some());
+        assert!(widget.state.input_error.is_some());
+
+        current_input(&mut widget).clear();
+        type_text(&mut widget, "+1h");
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert!(widget.state.input_target.is_none());
+        assert_eq!(widget.state.items.todo_items[0].due_state(chrono::Utc::now()), Some(DueState::DueSoon));
+    }
+
+    #[test]
+    fn shift_arrows_move_the_highlighted_item() {
+        let mut widget = new_widget();
+        widget.state.items.add_item("Feed dogs".to_string());
+        widget.state.items.add_item("Write code".to_string());
+        let shift_up = event::Event::Key(event::KeyEvent::new(event::KeyCode::Up, event::KeyModifiers::SHIFT));
+        widget.handle_event(&shift_up);
+        assert_eq!(widget.state.items.todo_items[0].title, "Write code");
+        assert_eq!(widget.state.items.selected_item().unwrap().title, "Write code");
+        assert_eq!(widget.state.store.load().unwrap().todo_items[0].title, "Write code");
+
+        widget.handle_event(&key(event::KeyCode::Char('s')));
+        widget.handle_event(&shift_up);
+        let rows = screen_rows(&mut widget);
+        assert!(rows[19].starts_with("sorted by age, change sort to manual"), "{}", rows[19]);
+        widget.handle_event(&key(event::KeyCode::Down));
+        assert_eq!(widget.state.input_error, None);
+    }
+
+    #[test]
+    fn marked_items_are_completed_together_and_undone_together() {
+        let mut widget = new_widget();
+        for title in ["Buy groceries", "Feed dogs", "Write code", "Fix door"] {
+            widget.handle_event(&key(event::KeyCode::Char('a')));
+            type_text(&mut widget, title);
+            widget.handle_event(&key(event::KeyCode::Enter));
+        }
+        widget.state.items.highlighted_todo_item = 0;
+        let shift_down = event::Event::Key(event::KeyEvent::new(event::KeyCode::Down, event::KeyModifiers::SHIFT));
+        widget.handle_event(&key(event::KeyCode::Char('v')));
+        widget.handle_event(&shift_down);
+        widget.handle_event(&key(event::KeyCode::Down));
+        widget.handle_event(&key(event::KeyCode::Down));
+        widget.handle_event(&key(event::KeyCode::Char(' ')));
+        let rows = screen_rows(&mut widget);
+        assert!(rows[0].contains("marked: 3"), "{}", rows[0]);
+
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert_eq!(titles(&widget.state.items.done_items), vec!["Buy groceries", "Feed dogs", "Fix door"]);
+        assert_eq!(widget.take_published_events().len(), 3);
+        assert!(!widget.state.visual);
+
+        widget.handle_event(&key(event::KeyCode::Char('u')));
+        assert_eq!(widget.state.items.todo_items.len(), 4);
+        let events = widget.take_published_events();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(&events[0], AppEvent::TodoReopened { title, .. } if title == "Buy groceries"));
+    }
+
+    #[test]
+    fn undo_redo_and_renames_reach_other_widgets() {
+        let mut store = MemoryStore::default();
+        let mut working = TodoItem::new("Feed dgos".to_string(), chrono::Utc::now());
+        working.working = true;
+        store.save(&TodoItems::new(vec![working])).unwrap();
+        let mut widget = new(60, 20, 0, 0, Box::new(store));
+        widget.init();
+        let id = widget.state.items.todo_items[0].id;
+        let working = |title: &str, working| AppEvent::WorkingChanged { id, title: title.to_string(), working };
+        assert_eq!(widget.take_published_events(), vec![working("Feed dgos", true)]);
+
+        widget.handle_event(&key(event::KeyCode::Char('e')));
+        for _ in 0..3 {
+            widget.handle_event(&key(event::KeyCode::Backspace));
+        }
+        type_text(&mut widget, "ogs");
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert_eq!(widget.take_published_events(), vec![working("Feed dogs", true)]);
+
+        widget.handle_event(&key(event::KeyCode::Char('w')));
+        widget.handle_event(&key(event::KeyCode::Char('u')));
+        assert_eq!(widget.take_published_events(), vec![working("Feed dogs", false), working("Feed dogs", true)]);
+
+        widget.handle_event(&key(event::KeyCode::Enter));
+        widget.handle_event(&key(event::KeyCode::Char('u')));
+        assert_eq!(widget.take_published_events(), vec![
+            working("Feed dogs", false),
+            AppEvent::TodoCompleted { id, title: "Feed dogs".to_string() },
+            AppEvent::TodoReopened { id, title: "Feed dogs".to_string() },
+            working("Feed dogs", true),
+        ]);
+        let redo = event::Event::Key(event::KeyEvent::new(event::KeyCode::Char('r'), event::KeyModifiers::CONTROL));
+        widget.handle_event(&redo);
+        assert_eq!(widget.take_published_events(), vec![
+            working("Feed dogs", false),
+            AppEvent::TodoCompleted { id, title: "Feed dogs".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn marked_items_can_be_tagged_and_escape_clears_the_marks() {
+        let mut widget = new_widget();
+        widget.state.items.add_item("Feed dogs".to_string());
+        widget.handle_event(&key(event::KeyCode::Char(' ')));
+        widget.handle_event(&key(event::KeyCode::Char('t')));
+        type_text(&mut widget, "pets");
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert_eq!(widget.state.items.todo_items[0].tags, vec!["pets"]);
+        assert!(widget.state.visual);
+
+        assert_eq!(widget.handle_event(&key(event::KeyCode::Esc)), EventResult::Consumed);
+        assert!(!widget.state.visual);
+        assert_eq!(widget.state.items.marked.len(), 0);
+    }
+
+    #[test]
+    fn keys_marking_doesnt_use_are_left_unhandled() {
+        let mut widget = new_widget();
+        widget.state.items.add_item("Feed dogs".to_string());
+        widget.handle_event(&key(event::KeyCode::Char('v')));
+        assert_eq!(widget.handle_event(&key(event::KeyCode::Tab)), EventResult::Unhandled);
+        assert_eq!(widget.handle_event(&key(event::KeyCode::BackTab)), EventResult::Unhandled);
+        assert!(widget.state.visual);
+    }
+
+    #[test]
+    fn marked_items_are_exported_to_a_file() {
+        let path = std::env::temp_dir().join(format!("termod-test-{}-marked.csv", std::process::id()));
+        let mut widget = new_widget();
+        for title in ["Feed dogs", "Write code"] {
+            widget.state.items.add_item(title.to_string());
+        }
+        widget.handle_event(&key(event::KeyCode::Char(' ')));
+        widget.handle_event(&key(event::KeyCode::Char('m')));
+        current_input(&mut widget).clear();
+        type_text(&mut widget, "pdf marked.pdf");
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert_eq!(widget.state.input_error, Some("unknown format pdf".to_string()));
+
+        current_input(&mut widget).clear();
+        type_text(&mut widget, &format!("csv {}", path.display()));
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert!(widget.state.input_target.is_none());
+        let rows = screen_rows(&mut widget);
+        assert!(rows[19].starts_with("Exported 1 todos to "), "{}", rows[19]);
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.contains("Write code"), "{}", csv);
+    }
+
+    #[test]
+    fn the_report_asks_for_a_date_range() {
+        let mut widget = new_widget();
+        widget.handle_event(&key(event::KeyCode::Char('R')));
+        assert_eq!(current_input(&mut widget).text(), "yesterday..today");
+        current_input(&mut widget).clear();
+        type_text(&mut widget, "last week");
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert!(widget.state.input_error.is_some());
+        assert_eq!(widget.take_navigation_requests().len(), 0);
+
+        current_input(&mut widget).clear();
+        type_text(&mut widget, "7d");
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert!(widget.state.input_target.is_none());
+        match widget.take_navigation_requests().pop() {
+            Some(Navigation::Push { view, modal: false }) => assert_eq!(view.get_title(), "Report"),
+            _ => panic!("expected the report to be pushed"),
+        }
+    }
+
+    #[test]
+    fn completing_a_repeating_item_brings_it_back() {
+        let mut widget = new_widget();
+        widget.state.items.add_item("Feed dogs".to_string());
+        widget.handle_event(&key(event::KeyCode::Char('r')));
+        type_text(&mut widget, "weekly mon,thu");
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert_eq!(widget.state.items.todo_items[0].recurrence, Recurrence::parse("weekly mon,thu").unwrap());
+
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert_eq!(widget.state.items.done_items.len(), 1);
+        assert_eq!(widget.state.items.todo_items.len(), 1);
+        assert!(widget.state.items.todo_items[0].due_at.is_some());
+        let rows = screen_rows(&mut widget);
+        assert!(rows[1].contains("- ↻ Feed dogs"), "{}", rows[1]);
+    }
+
+    #[test]
+    fn filters_hide_items_and_show_in_the_header() {
+        let mut widget = new_widget();
+        widget.state.items.add_item("Feed dogs".to_string());
+        widget.state.items.add_item("Write code".to_string());
+        widget.handle_event(&key(event::KeyCode::Char('t')));
+        type_text(&mut widget, "work");
+        widget.handle_event(&key(event::KeyCode::Enter));
+
+        widget.handle_event(&key(event::KeyCode::Char('f')));
+        type_text(&mut widget, "work");
+        widget.handle_event(&key(event::KeyCode::Enter));
+        widget.handle_event(&key(event::KeyCode::Char('s')));
+
+        let rows = screen_rows(&mut widget);
+        assert!(rows[0].contains("TODO 1/2 sort:age"), "{}", rows[0]);
+        assert!(rows[0].contains("filter: #work"), "{}", rows[0]);
+        assert!(rows[1].contains("Write code"), "{}", rows[1]);
+        assert!(!rows[2].contains("Feed dogs"), "{}", rows[2]);
+    }
+
+    #[test]
+    fn the_highlighted_item_stays_on_screen_in_long_lists() {
+        let mut widget = new_widget();
+        for i in 0..200 {
+            widget.state.items.push_item(TodoItem::new(format!("item {}", i), chrono::Utc::now()));
+        }
+        for _ in 0..150 {
+            widget.handle_event(&key(event::KeyCode::Down));
+        }
+
+        let last_todo_row = &screen_rows(&mut widget)[9];
+        assert!(last_todo_row.contains("item 150"), "{}", last_todo_row);
+    }
+
+    #[test]
+    fn each_kind_of_prompt_has_its_own_history() {
+        let mut widget = new_widget();
+        widget.handle_event(&key(event::KeyCode::Char('a')));
+        type_text(&mut widget, "Feed dogs");
+        widget.handle_event(&key(event::KeyCode::Enter));
+
+        widget.handle_event(&key(event::KeyCode::Char('f')));
+        widget.handle_event(&key(event::KeyCode::Up));
+        assert_eq!(current_input(&mut widget).text(), "");
+        widget.handle_event(&key(event::KeyCode::Esc));
+
+        widget.handle_event(&key(event::KeyCode::Char('e')));
+        current_input(&mut widget).clear();
+        widget.handle_event(&key(event::KeyCode::Up));
+        assert_eq!(current_input(&mut widget).text(), "Feed dogs");
+    }
+
+    #[test]
+    fn search_narrows_the_list_and_enter_jumps_to_the_match() {
+        let mut widget = new_widget();
+        for title in ["Buy groceries", "Feed dogs", "Write code", "Fix door"] {
+            widget.state.items.add_item(title.to_string());
+        }
+        widget.state.items.highlighted_todo_item = 0;
+        widget.handle_event(&key(event::KeyCode::Char('/')));
+        type_text(&mut widget, "fd");
+        assert_eq!(widget.state.items.visible(Section::TODO), vec![1, 3]);
+        widget.handle_event(&key(event::KeyCode::Down));
+
+        let rows = screen_rows(&mut widget);
+        assert!(rows[1].contains("Feed dogs"), "{}", rows[1]);
+        let column = rows[1].chars().position(|c| c == 'F').unwrap();
+        assert_eq!(widget.generate_buffer()[60 + column].colour, colours::YELLOW);
+
+        widget.handle_event(&key(event::KeyCode::Enter));
+        assert!(widget.state.input_target.is_none());
+        assert_eq!(widget.state.items.visible(Section::TODO).len(), 4);
+        assert_eq!(widget.state.items.selected_item().unwrap().title, "Fix door");
+    }
+
+    #[test]
+    fn esc_ends_a_search_where_it_started() {
+        let mut widget = new_widget();
+        widget.state.items.add_item("Buy groceries".to_string());
+        widget.state.items.add_item("Feed dogs".to_string());
+        widget.handle_event(&key(event::KeyCode::Char('/')));
+        type_text(&mut widget, "buy");
+        assert_eq!(widget.state.items.selected_item().unwrap().title, "Buy groceries");
+
+        assert_eq!(widget.handle_event(&key(event::KeyCode::Esc)), EventResult::Consumed);
+        assert_eq!(widget.state.items.filter.search, None);
+        assert_eq!(widget.state.items.selected_item().unwrap().title, "Feed dogs");
+    }
+
+    #[test]
+    fn subtasks_are_indented_with_progress_on_the_parent() {
+        let mut widget = new_widget();
+        widget.state.items.add_item("Move house".to_string());
+        widget.handle_event(&key(event::KeyCode::Char('A')));
+        type_text(&mut widget, "Pack");
+        widget.handle_event(&key(event::KeyCode::Enter));
+
+        let rows = screen_rows(&mut widget);
+        assert!(rows[1].contains("- ▾ Move house 0/1"), "{}", rows[1]);
+        assert!(rows[2].contains("-   Pack"), "{}", rows[2]);
+
+        widget.handle_event(&key(event::KeyCode::Up));
+        widget.handle_event(&key(event::KeyCode::Char('c')));
+        let rows = screen_rows(&mut widget);
+        assert!(rows[1].contains("- ▸ Move house"), "{}", rows[1]);
+        assert!(!rows[2].contains("Pack"), "{}", rows[2]);
+    }
+}
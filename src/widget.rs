@@ -1,26 +1,47 @@
 use crossterm::{event, style};
+use crate::app::Navigation;
 use crate::buffer::Buffer;
 use crate::character::{self, Character};
+use crate::event_bus::{AppEvent, AppEventKind};
+
+// Whether a widget dealt with an event. Unhandled events bubble up to the
+// parent and finally to the app's global shortcuts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventResult {
+    Consumed,
+    Unhandled,
+}
 
 pub struct Widget<T> {
     pub width: u16,
     pub height: u16,
     pub title: String,
     pub init_fn: fn(&mut Widget<T>),
-    pub event_fn: fn(&mut Widget<T>, &event::Event),
+    pub event_fn: fn(&mut Widget<T>, &event::Event) -> EventResult,
+    pub app_event_fn: fn(&mut Widget<T>, &AppEvent),
     pub update_fn: fn(&mut Widget<T>),
     pub draw_fn: fn(&mut Widget<T>),
     pub generate_buffer_fn: fn(&mut Widget<T>) -> Buffer,
+    pub subscriptions: Vec<AppEventKind>,
+    pub published_events: Vec<AppEvent>,
+    pub navigation_requests: Vec<Navigation>,
+    // Where the terminal cursor should be shown, relative to the widget. Set while drawing.
+    pub cursor_position: Option<(u16, u16)>,
     pub state: T,
 }
 
 pub trait WidgetTrait {
     fn init(&mut self);
-    fn handle_event(&mut self, event_to_handle: &event::Event);
+    fn handle_event(&mut self, event_to_handle: &event::Event) -> EventResult;
+    fn handle_app_event(&mut self, app_event: &AppEvent);
+    fn take_published_events(&mut self) -> Vec<AppEvent>;
+    fn take_navigation_requests(&mut self) -> Vec<Navigation>;
     fn update(&mut self);
     fn draw(&mut self);
     fn generate_buffer(&mut self) -> Buffer;
-    fn get_title(&self) -> &String; 
+    fn get_title(&self) -> &String;
+    fn get_size(&self) -> (u16, u16);
+    fn get_cursor_position(&self) -> Option<(u16, u16)>;
 }
 
 impl<T> Widget<T> {
@@ -28,8 +49,34 @@ impl<T> Widget<T> {
         (self.init_fn)(self);
     }
     
-    pub fn handle_event(&mut self, event_to_handle: &event::Event) {
-        (self.event_fn)(self, event_to_handle);
+    pub fn handle_event(&mut self, event_to_handle: &event::Event) -> EventResult {
+        return (self.event_fn)(self, event_to_handle);
+    }
+
+    pub fn handle_app_event(&mut self, app_event: &AppEvent) {
+        if self.subscriptions.contains(&app_event.kind()) {
+            (self.app_event_fn)(self, app_event);
+        }
+    }
+
+    pub fn publish(&mut self, app_event: AppEvent) {
+        self.published_events.push(app_event);
+    }
+
+    pub fn take_published_events(&mut self) -> Vec<AppEvent> {
+        return std::mem::take(&mut self.published_events);
+    }
+
+    pub fn push_view(&mut self, view: Box<dyn WidgetTrait>, modal: bool) {
+        self.navigation_requests.push(Navigation::Push { view, modal });
+    }
+
+    pub fn pop_view(&mut self) {
+        self.navigation_requests.push(Navigation::Pop);
+    }
+
+    pub fn take_navigation_requests(&mut self) -> Vec<Navigation> {
+        return std::mem::take(&mut self.navigation_requests);
     }
 
     pub fn update(&mut self) {
@@ -43,9 +90,11 @@ impl<T> Widget<T> {
     pub fn generate_buffer(&mut self) -> Buffer {
         return (self.generate_buffer_fn)(self);
     }
-    
 }
 
+// For widgets that don't subscribe to any app events.
+pub fn ignore_app_event<T>(_myself: &mut Widget<T>, _app_event: &AppEvent) {}
+
 pub fn add_buffer_border(buffer: &mut Buffer, colour: style::Color) {
     let buffer_width = buffer.width as usize;
     let top_bottom_str = &"-".repeat(buffer_width - 2).to_string();
@@ -62,8 +111,8 @@ pub fn add_buffer_border(buffer: &mut Buffer, colour: style::Color) {
                 attributes: character::empty_attr_set(),
                 colour,
             };
-            buffer[buffer_width as usize * y as usize] = character.clone();
-            buffer[buffer_width as usize * y as usize + buffer_width-1] = character.clone();
+            buffer[buffer_width * y as usize] = character.clone();
+            buffer[buffer_width * y as usize + buffer_width-1] = character.clone();
         }
     }
 }